@@ -1,7 +1,21 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { PublicKey, Keypair, SystemProgram } from "@solana/web3.js";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
 import { assert } from "chai";
+import { keccak_256 } from "@noble/hashes/sha3";
 
 describe("Layerx402 Smart Contracts", () => {
   // Configure the client
@@ -10,27 +24,210 @@ describe("Layerx402 Smart Contracts", () => {
 
   const payer = provider.wallet as anchor.Wallet;
 
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  // Asserts that `promise` fails with the given program error code
+  async function expectError(promise: Promise<unknown>, code: string) {
+    try {
+      await promise;
+    } catch (err) {
+      assert.include(String(err), code);
+      return;
+    }
+    assert.fail(`expected ${code}`);
+  }
+
   describe("Payment Escrow", () => {
     let escrowAccount: PublicKey;
     let recipient: Keypair;
     const amount = new anchor.BN(1_000_000_000); // 1 SOL
+    const escrowId = new anchor.BN(Date.now());
 
     before(async () => {
       recipient = Keypair.generate();
     });
 
+    // Default `InitializeEscrowArgs`: a plain one-hour SOL escrow paying `recipient`
+    const escrowTerms = (escrowAmount: anchor.BN) => ({
+      amount: escrowAmount,
+      recipient: recipient.publicKey,
+      expiryTimestamp: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+      mint: null,
+      proofHash: null,
+      milestones: [],
+      arbiter: null,
+      facilitator: null,
+      keeperTip: new anchor.BN(0),
+      recipients: [],
+      requireVerifiedPayment: false,
+    });
+
+    // Creates and fully funds a SOL escrow paying `recipient`
+    async function createFundedEscrow(
+      options: {
+        expiresIn?: number;
+        keeperTip?: anchor.BN;
+        recipients?: { recipient: PublicKey; shareBps: number }[];
+        proofHash?: number[];
+        milestones?: {
+          amount: anchor.BN;
+          deadline: anchor.BN | null;
+          proofHash: number[] | null;
+          released: boolean;
+        }[];
+        arbiter?: PublicKey;
+        facilitator?: PublicKey;
+      } = {}
+    ): Promise<PublicKey> {
+      const program = anchor.workspace.PaymentEscrow as Program;
+      const id = new anchor.BN(Date.now() + Math.floor(Math.random() * 1000));
+
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          payer.publicKey.toBuffer(),
+          id.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      await program.methods
+        .initializeEscrow(id, {
+          ...escrowTerms(amount),
+          expiryTimestamp: new anchor.BN(
+            Math.floor(Date.now() / 1000) + (options.expiresIn ?? 3600)
+          ),
+          proofHash: options.proofHash ?? null,
+          milestones: options.milestones ?? [],
+          arbiter: options.arbiter ?? null,
+          facilitator: options.facilitator ?? null,
+          keeperTip: options.keeperTip ?? new anchor.BN(0),
+          recipients: options.recipients ?? [],
+        })
+        .accounts({
+          escrow,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await program.methods
+        .fundEscrow(amount)
+        .accounts({
+          escrow,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      return escrow;
+    }
+
+    // Creates an unfunded token escrow of `tokenAmount` base units of `mint`
+    async function createTokenEscrow(
+      mint: PublicKey,
+      tokenAmount: anchor.BN,
+      options: { expiresIn?: number } = {}
+    ): Promise<{ escrow: PublicKey; vault: PublicKey }> {
+      const program = anchor.workspace.PaymentEscrow as Program;
+      const id = new anchor.BN(Date.now() + Math.floor(Math.random() * 1000));
+
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          payer.publicKey.toBuffer(),
+          id.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), escrow.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initializeEscrow(id, {
+          ...escrowTerms(tokenAmount),
+          expiryTimestamp: new anchor.BN(
+            Math.floor(Date.now() / 1000) + (options.expiresIn ?? 3600)
+          ),
+          mint,
+        })
+        .accounts({
+          escrow,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      return { escrow, vault };
+    }
+
+    // Creates the vault of a token escrow and deposits its full amount
+    async function fundTokenEscrow(
+      escrow: PublicKey,
+      vault: PublicKey,
+      mint: PublicKey,
+      payerToken: PublicKey
+    ) {
+      const program = anchor.workspace.PaymentEscrow as Program;
+      const escrowData = await program.account.escrow.fetch(escrow);
+
+      await program.methods
+        .initializeEscrowVault()
+        .accounts({
+          escrow,
+          mint,
+          vault,
+          payer: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+
+      await program.methods
+        .fundEscrow(escrowData.amount)
+        .accounts({
+          escrow,
+          payer: payer.publicKey,
+          payerToken,
+          vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    const tokenAccount = async (mint: PublicKey, owner: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          payer.payer,
+          mint,
+          owner
+        )
+      ).address;
+
     it("Initializes an escrow", async () => {
       const program = anchor.workspace.PaymentEscrow as Program;
 
       const expiryTimestamp = Math.floor(Date.now() / 1000) + 3600; // 1 hour
 
       [escrowAccount] = PublicKey.findProgramAddressSync(
-        [Buffer.from("escrow"), payer.publicKey.toBuffer()],
+        [
+          Buffer.from("escrow"),
+          payer.publicKey.toBuffer(),
+          escrowId.toArrayLike(Buffer, "le", 8),
+        ],
         program.programId
       );
 
       await program.methods
-        .initializeEscrow(amount, recipient.publicKey, new anchor.BN(expiryTimestamp))
+        .initializeEscrow(escrowId, {
+          ...escrowTerms(amount),
+          expiryTimestamp: new anchor.BN(expiryTimestamp),
+        })
         .accounts({
           escrow: escrowAccount,
           payer: payer.publicKey,
@@ -42,6 +239,7 @@ describe("Layerx402 Smart Contracts", () => {
       assert.ok(escrowData.payer.equals(payer.publicKey));
       assert.ok(escrowData.recipient.equals(recipient.publicKey));
       assert.ok(escrowData.amount.eq(amount));
+      assert.ok(escrowData.escrowId.eq(escrowId));
     });
 
     it("Funds the escrow", async () => {
@@ -50,7 +248,7 @@ describe("Layerx402 Smart Contracts", () => {
       const balanceBefore = await provider.connection.getBalance(escrowAccount);
 
       await program.methods
-        .fundEscrow()
+        .fundEscrow(amount)
         .accounts({
           escrow: escrowAccount,
           payer: payer.publicKey,
@@ -60,6 +258,10 @@ describe("Layerx402 Smart Contracts", () => {
 
       const balanceAfter = await provider.connection.getBalance(escrowAccount);
       assert.ok(balanceAfter > balanceBefore);
+
+      const escrowData = await program.account.escrow.fetch(escrowAccount);
+      assert.ok(escrowData.fundedAmount.eq(amount));
+      assert.equal(escrowData.status.funded, true);
     });
 
     it("Releases the escrow with payment proof", async () => {
@@ -87,185 +289,1754 @@ describe("Layerx402 Smart Contracts", () => {
       const escrowData = await program.account.escrow.fetch(escrowAccount);
       assert.equal(escrowData.status.released, true);
     });
-  });
 
-  describe("Payment Verification", () => {
-    let verifierAccount: PublicKey;
-    let paymentAccount: PublicKey;
-    const paymentProof = "TEST_PAYMENT_PROOF_" + Date.now();
+    it("Releases a hashlocked escrow only for the committed preimage", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
 
-    it("Initializes the verifier", async () => {
-      const program = anchor.workspace.PaymentVerification as Program;
+      const preimage = "x402_PROOF_" + Date.now();
+      const escrow = await createFundedEscrow({ proofHash: [...keccak_256(preimage)] });
 
-      [verifierAccount] = PublicKey.findProgramAddressSync(
-        [Buffer.from("verifier")],
-        program.programId
-      );
+      const release = (proof: string) =>
+        program.methods
+          .releaseEscrow(proof)
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            authority: payer.publicKey,
+          })
+          .rpc();
+
+      await expectError(release("WRONG_PROOF"), "InvalidPaymentProof");
+
+      await release(preimage);
+
+      const escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.status.released, true);
+      assert.equal(escrowData.paymentProof, preimage);
+    });
+
+    it("Releases milestone tranches one at a time and refunds the rest", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+
+      const first = new anchor.BN(300_000_000);
+      const rest = amount.sub(first);
+      const escrow = await createFundedEscrow({
+        milestones: [
+          {
+            amount: first,
+            deadline: null,
+            proofHash: [...keccak_256("TRANCHE_0")],
+            released: false,
+          },
+          { amount: rest, deadline: null, proofHash: null, released: false },
+        ],
+      });
+
+      const releaseMilestone = (index: number, proof: string) =>
+        program.methods
+          .releaseMilestone(index, proof)
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            authority: payer.publicKey,
+          })
+          .rpc();
+
+      // Each tranche is locked to its own preimage
+      await expectError(releaseMilestone(0, "TRANCHE_1"), "InvalidPaymentProof");
+
+      await releaseMilestone(0, "TRANCHE_0");
+
+      let escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.status.partiallyReleased, true);
+      assert.ok(escrowData.releasedAmount.eq(first));
+      assert.equal(escrowData.milestones[0].released, true);
 
+      await expectError(releaseMilestone(0, "TRANCHE_0"), "MilestoneAlreadyReleased");
+
+      // Refunding returns only the unreleased tranche
+      const escrowBefore = await provider.connection.getBalance(escrow);
       await program.methods
-        .initializeVerifier()
-        .accounts({
-          verifier: verifierAccount,
-          authority: payer.publicKey,
-          systemProgram: SystemProgram.programId,
-        })
+        .refundEscrow()
+        .accounts({ escrow, payer: payer.publicKey })
         .rpc();
 
-      const verifierData = await program.account.verifier.fetch(verifierAccount);
-      assert.ok(verifierData.authority.equals(payer.publicKey));
-      assert.ok(verifierData.totalVerifications.eq(new anchor.BN(0)));
-    });
+      const escrowAfter = await provider.connection.getBalance(escrow);
+      assert.equal(escrowBefore - escrowAfter, rest.toNumber());
 
-    it("Verifies a payment", async () => {
-      const program = anchor.workspace.PaymentVerification as Program;
+      escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.status.refunded, true);
+    });
 
-      const recipient = Keypair.generate().publicKey;
-      const amount = new anchor.BN(500_000_000); // 0.5 SOL
+    it("Skips milestones whose deadline has passed", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
 
-      [paymentAccount] = PublicKey.findProgramAddressSync(
-        [
-          Buffer.from("payment"),
-          payer.publicKey.toBuffer(),
-          Buffer.from(paymentProof),
+      const half = amount.divn(2);
+      const escrow = await createFundedEscrow({
+        milestones: [
+          {
+            amount: half,
+            deadline: new anchor.BN(Math.floor(Date.now() / 1000) + 3),
+            proofHash: null,
+            released: false,
+          },
+          { amount: half, deadline: null, proofHash: null, released: false },
         ],
-        program.programId
+      });
+      await sleep(5000);
+
+      const accounts = {
+        escrow,
+        recipient: recipient.publicKey,
+        authority: payer.publicKey,
+      };
+
+      await expectError(
+        program.methods.releaseMilestone(0, "LATE_PROOF").accounts(accounts).rpc(),
+        "MilestoneExpired"
       );
 
+      // The next-tranche release moves on to the tranche still open
+      await program.methods.releaseEscrow("LATE_PROOF").accounts(accounts).rpc();
+
+      const escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.milestones[0].released, false);
+      assert.equal(escrowData.milestones[1].released, true);
+      assert.ok(escrowData.releasedAmount.eq(half));
+      assert.equal(escrowData.status.partiallyReleased, true);
+    });
+
+    it("Binds a facilitator release attestation to its milestone", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+      const facilitator = Keypair.generate();
+      const relayer = Keypair.generate();
+
+      const half = amount.divn(2);
+      const escrow = await createFundedEscrow({
+        milestones: [
+          { amount: half, deadline: null, proofHash: null, released: false },
+          { amount: half, deadline: null, proofHash: null, released: false },
+        ],
+        facilitator: facilitator.publicKey,
+      });
+
+      // Both tranches have the same amount and released total, so only the index tells them apart
+      const attestation = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: facilitator.secretKey,
+        message: Buffer.concat([
+          escrow.toBuffer(),
+          recipient.publicKey.toBuffer(),
+          half.toArrayLike(Buffer, "le", 8),
+          new anchor.BN(0).toArrayLike(Buffer, "le", 8),
+          Buffer.from([1]),
+        ]),
+      });
+      const releaseMilestone = (index: number) =>
+        program.methods
+          .releaseMilestone(index, "FACILITATED_PROOF")
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            authority: relayer.publicKey,
+          })
+          .preInstructions([attestation])
+          .signers([relayer])
+          .rpc();
+
+      await expectError(releaseMilestone(0), "InvalidAttestation");
+      await releaseMilestone(1);
+
+      const escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.milestones[0].released, false);
+      assert.equal(escrowData.milestones[1].released, true);
+      assert.ok(escrowData.releasedAmount.eq(half));
+    });
+
+    it("Freezes a disputed escrow until the arbiter splits it", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+
+      const arbiter = Keypair.generate();
+      const escrow = await createFundedEscrow({ arbiter: arbiter.publicKey });
+
       await program.methods
-        .verifyPayment(
-          paymentProof,
-          amount,
-          recipient,
-          "solana",
-          "5wHu7J9VqYZN8xN9xN9xN9xN9xN9xN9xN9xN9xN9xN9x"
-        )
-        .accounts({
-          payment: paymentAccount,
-          verifier: verifierAccount,
-          payer: payer.publicKey,
-          systemProgram: SystemProgram.programId,
-        })
+        .raiseEscrowDispute("Service not delivered")
+        .accounts({ escrow, disputer: payer.publicKey })
         .rpc();
 
-      const paymentData = await program.account.payment.fetch(paymentAccount);
-      assert.ok(paymentData.amount.eq(amount));
-      assert.ok(paymentData.recipient.equals(recipient));
-      assert.equal(paymentData.network, "solana");
-      assert.equal(paymentData.status.verified, true);
+      let escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.status.disputed, true);
 
-      const verifierData = await program.account.verifier.fetch(verifierAccount);
-      assert.ok(verifierData.totalVerifications.eq(new anchor.BN(1)));
+      // Neither side can move the funds while the dispute is open
+      await expectError(
+        program.methods
+          .releaseEscrow("DISPUTED_PROOF")
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            authority: payer.publicKey,
+          })
+          .rpc(),
+        "InvalidEscrowStatus"
+      );
+      await expectError(
+        program.methods
+          .refundEscrow()
+          .accounts({ escrow, payer: payer.publicKey })
+          .rpc(),
+        "InvalidEscrowStatus"
+      );
+
+      // The timeout refund is not available yet
+      await expectError(
+        program.methods
+          .expireEscrowDispute()
+          .accounts({ escrow, payer: payer.publicKey })
+          .rpc(),
+        "DisputeTimeoutNotReached"
+      );
+
+      const resolve = (signer: Keypair) =>
+        program.methods
+          .arbiterResolve(2500) // 25% to the recipient
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            payer: payer.publicKey,
+            arbiter: signer.publicKey,
+          })
+          .signers([signer])
+          .rpc();
+
+      await expectError(resolve(recipient), "Unauthorized");
+
+      const recipientBefore = await provider.connection.getBalance(recipient.publicKey);
+      const payerBefore = await provider.connection.getBalance(payer.publicKey);
+      const escrowBefore = await provider.connection.getBalance(escrow);
+
+      await resolve(arbiter);
+
+      const recipientAfter = await provider.connection.getBalance(recipient.publicKey);
+      const payerAfter = await provider.connection.getBalance(payer.publicKey);
+      const escrowAfter = await provider.connection.getBalance(escrow);
+
+      assert.equal(recipientAfter - recipientBefore, 250_000_000);
+      assert.equal(escrowBefore - escrowAfter, amount.toNumber());
+      // The payer also paid the transaction fee
+      assert.isAbove(payerAfter - payerBefore, 750_000_000 - 100_000);
+
+      escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.status.resolved, true);
+      assert.ok(escrowData.releasedAmount.eqn(250_000_000));
     });
 
-    it("Settles a payment", async () => {
-      const program = anchor.workspace.PaymentVerification as Program;
+    it("Amends an escrow with both parties' approval", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+
+      const escrow = await createFundedEscrow();
+      const newExpiry = new anchor.BN(Math.floor(Date.now() / 1000) + 7200);
 
       await program.methods
-        .settlePayment()
+        .amendEscrow(0, newExpiry, null, null)
         .accounts({
-          payment: paymentAccount,
-          verifier: verifierAccount,
-          authority: payer.publicKey,
+          escrow,
+          payer: payer.publicKey,
+          recipient: recipient.publicKey,
         })
+        .signers([recipient])
         .rpc();
 
-      const paymentData = await program.account.payment.fetch(paymentAccount);
-      assert.equal(paymentData.status.settled, true);
-      assert.ok(paymentData.settledAt !== null);
+      const escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.version, 1);
+      assert.ok(escrowData.expiryTimestamp.eq(newExpiry));
+
+      // An approval signed against version 0 is now stale
+      await expectError(
+        program.methods
+          .amendEscrow(0, null, null, null)
+          .accounts({
+            escrow,
+            payer: payer.publicKey,
+            recipient: recipient.publicKey,
+          })
+          .signers([recipient])
+          .rpc(),
+        "StaleAmendment"
+      );
     });
-  });
 
-  describe("Payment Settlement", () => {
-    let poolAccount: PublicKey;
-    let settlementAccount: PublicKey;
-    const paymentId = "PAYMENT_" + Date.now();
+    it("Splits a release between recipients by share", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
 
-    it("Initializes the settlement pool", async () => {
-      const program = anchor.workspace.PaymentSettlement as Program;
+      const partner = Keypair.generate().publicKey;
+      const escrow = await createFundedEscrow({
+        recipients: [
+          { recipient: recipient.publicKey, shareBps: 6000 },
+          { recipient: partner, shareBps: 4000 },
+        ],
+      });
 
-      [poolAccount] = PublicKey.findProgramAddressSync(
-        [Buffer.from("pool")],
-        program.programId
+      // Without one destination per share the release is rejected
+      await expectError(
+        program.methods
+          .releaseEscrow("SPLIT_PROOF")
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            authority: payer.publicKey,
+          })
+          .rpc(),
+        "MissingRecipientAccounts"
       );
 
-      const feePercentage = 100; // 1%
+      const recipientBefore = await provider.connection.getBalance(recipient.publicKey);
 
       await program.methods
-        .initializePool(feePercentage)
+        .releaseEscrow("SPLIT_PROOF")
         .accounts({
-          pool: poolAccount,
+          escrow,
+          recipient: recipient.publicKey,
           authority: payer.publicKey,
-          systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts([
+          { pubkey: recipient.publicKey, isSigner: false, isWritable: true },
+          { pubkey: partner, isSigner: false, isWritable: true },
+        ])
         .rpc();
 
-      const poolData = await program.account.settlementPool.fetch(poolAccount);
-      assert.ok(poolData.authority.equals(payer.publicKey));
-      assert.equal(poolData.feePercentage, feePercentage);
-      assert.ok(poolData.totalSettled.eq(new anchor.BN(0)));
+      const recipientAfter = await provider.connection.getBalance(recipient.publicKey);
+      assert.equal(recipientAfter - recipientBefore, 600_000_000);
+      assert.equal(await provider.connection.getBalance(partner), 400_000_000);
     });
 
-    it("Creates a settlement", async () => {
-      const program = anchor.workspace.PaymentSettlement as Program;
+    it("Funds and releases a token escrow through its vault", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
 
-      const recipient = Keypair.generate().publicKey;
-      const amount = new anchor.BN(1_000_000_000); // 1 SOL
+      const mint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+      const otherMint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+      const payerToken = await tokenAccount(mint, payer.publicKey);
+      await mintTo(provider.connection, payer.payer, mint, payerToken, payer.publicKey, 10_000_000);
 
-      [settlementAccount] = PublicKey.findProgramAddressSync(
-        [
-          Buffer.from("settlement"),
-          payer.publicKey.toBuffer(),
-          Buffer.from(paymentId),
-        ],
-        program.programId
+      const tokenAmount = new anchor.BN(1_000_000);
+      const { escrow, vault } = await createTokenEscrow(mint, tokenAmount);
+
+      // The vault must use the escrow's mint
+      await expectError(
+        program.methods
+          .initializeEscrowVault()
+          .accounts({
+            escrow,
+            mint: otherMint,
+            vault,
+            payer: payer.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          })
+          .rpc(),
+        "InvalidMint"
+      );
+
+      await fundTokenEscrow(escrow, vault, mint, payerToken);
+      const escrowData = await program.account.escrow.fetch(escrow);
+      assert.equal(escrowData.status.funded, true);
+      assert.equal(
+        Number((await getAccount(provider.connection, vault)).amount),
+        tokenAmount.toNumber()
+      );
+
+      const release = (recipientToken: PublicKey) =>
+        program.methods
+          .releaseEscrow("TOKEN_PROOF")
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            recipientToken,
+            vault,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            authority: payer.publicKey,
+          })
+          .rpc();
+
+      // Tokens only go to a recipient-owned account of the escrow mint
+      await expectError(release(payerToken), "InvalidDestination");
+      await expectError(
+        release(await tokenAccount(otherMint, recipient.publicKey)),
+        "InvalidMint"
+      );
+
+      const recipientToken = await tokenAccount(mint, recipient.publicKey);
+      await release(recipientToken);
+
+      assert.equal(
+        Number((await getAccount(provider.connection, recipientToken)).amount),
+        tokenAmount.toNumber()
       );
+      assert.equal((await program.account.escrow.fetch(escrow)).status.released, true);
+    });
+
+    it("Refunds a token escrow to the payer and closes its vault", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+
+      const mint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+      const payerToken = await tokenAccount(mint, payer.publicKey);
+      await mintTo(provider.connection, payer.payer, mint, payerToken, payer.publicKey, 1_000_000);
+
+      const { escrow, vault } = await createTokenEscrow(mint, new anchor.BN(1_000_000));
+      await fundTokenEscrow(escrow, vault, mint, payerToken);
+      assert.equal(Number((await getAccount(provider.connection, payerToken)).amount), 0);
 
       await program.methods
-        .createSettlement(amount, recipient, paymentId)
+        .refundEscrow()
         .accounts({
-          settlement: settlementAccount,
-          pool: poolAccount,
+          escrow,
           payer: payer.publicKey,
-          systemProgram: SystemProgram.programId,
+          payerToken,
+          vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
         })
         .rpc();
 
-      const settlementData = await program.account.settlement.fetch(
-        settlementAccount
+      assert.equal(
+        Number((await getAccount(provider.connection, payerToken)).amount),
+        1_000_000
       );
-      assert.ok(settlementData.grossAmount.eq(amount));
-      assert.equal(settlementData.paymentId, paymentId);
-      assert.equal(settlementData.status.pending, true);
 
-      // Check fee calculation (1%)
-      const expectedFee = amount.muln(1).divn(100);
-      assert.ok(settlementData.feeAmount.eq(expectedFee));
+      await program.methods
+        .closeEscrow()
+        .accounts({
+          escrow,
+          payer: payer.publicKey,
+          vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      assert.isNull(await program.account.escrow.fetchNullable(escrow));
+      assert.isNull(await provider.connection.getAccountInfo(vault));
     });
 
-    it("Processes a settlement", async () => {
-      const program = anchor.workspace.PaymentSettlement as Program;
+    it("Closes a token escrow refunded before its vault was created", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
 
-      const settlementData = await program.account.settlement.fetch(
-        settlementAccount
-      );
-      const recipient = settlementData.recipient;
+      const mint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+      const { escrow, vault } = await createTokenEscrow(mint, new anchor.BN(1_000_000));
 
-      const recipientBalanceBefore = await provider.connection.getBalance(
-        recipient
-      );
+      await program.methods
+        .refundEscrow()
+        .accounts({ escrow, payer: payer.publicKey })
+        .rpc();
 
       await program.methods
-        .processSettlement()
+        .closeEscrow()
         .accounts({
-          settlement: settlementAccount,
-          pool: poolAccount,
+          escrow,
           payer: payer.publicKey,
-          recipient: recipient,
-          systemProgram: SystemProgram.programId,
+          vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
         })
         .rpc();
 
-      const recipientBalanceAfter = await provider.connection.getBalance(
+      assert.isNull(await program.account.escrow.fetchNullable(escrow));
+    });
+
+    it("Rejects cranking an escrow before expiry", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+
+      const escrow = await createFundedEscrow();
+
+      await expectError(
+        program.methods
+          .crankExpired()
+          .accounts({ keeper: payer.publicKey })
+          .remainingAccounts([
+            { pubkey: escrow, isSigner: false, isWritable: true },
+            { pubkey: payer.publicKey, isSigner: false, isWritable: true },
+          ])
+          .rpc(),
+        "EscrowNotExpired"
+      );
+    });
+
+    it("Cranks an expired escrow back to the payer", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+      const keeper = Keypair.generate();
+
+      // Large enough to leave the fresh keeper account rent-exempt
+      const keeperTip = new anchor.BN(1_000_000);
+      const escrow = await createFundedEscrow({ expiresIn: 3, keeperTip });
+      await sleep(5000);
+
+      const keeperBefore = await provider.connection.getBalance(keeper.publicKey);
+      const payerBefore = await provider.connection.getBalance(payer.publicKey);
+      const escrowBefore = await provider.connection.getBalance(escrow);
+
+      await program.methods
+        .crankExpired()
+        .accounts({ keeper: keeper.publicKey })
+        .remainingAccounts([
+          { pubkey: escrow, isSigner: false, isWritable: true },
+          { pubkey: payer.publicKey, isSigner: false, isWritable: true },
+        ])
+        .signers([keeper])
+        .rpc();
+
+      const keeperAfter = await provider.connection.getBalance(keeper.publicKey);
+      const payerAfter = await provider.connection.getBalance(payer.publicKey);
+
+      assert.equal(keeperAfter - keeperBefore, keeperTip.toNumber());
+      // The payer gets the held amount and the escrow's rent, less the transaction fee
+      const payerShare = escrowBefore - keeperTip.toNumber();
+      assert.isAbove(payerShare, amount.toNumber());
+      assert.isAbove(payerAfter - payerBefore, payerShare - 100_000);
+      assert.isNull(await program.account.escrow.fetchNullable(escrow));
+    });
+
+    it("Cranks a refunded escrow to close it and pay the keeper", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+      const keeper = Keypair.generate();
+
+      const keeperTip = new anchor.BN(1_000_000);
+      const escrow = await createFundedEscrow({ expiresIn: 3, keeperTip });
+
+      await program.methods
+        .refundEscrow()
+        .accounts({ escrow, payer: payer.publicKey })
+        .rpc();
+      await sleep(5000);
+
+      const escrowBefore = await provider.connection.getBalance(escrow);
+
+      await program.methods
+        .crankExpired()
+        .accounts({ keeper: keeper.publicKey })
+        .remainingAccounts([
+          { pubkey: escrow, isSigner: false, isWritable: true },
+          { pubkey: payer.publicKey, isSigner: false, isWritable: true },
+        ])
+        .signers([keeper])
+        .rpc();
+
+      // Nothing is held any more; only the tip and the rent remain
+      assert.isBelow(escrowBefore, amount.toNumber());
+      assert.equal(await provider.connection.getBalance(keeper.publicKey), keeperTip.toNumber());
+      assert.isNull(await program.account.escrow.fetchNullable(escrow));
+    });
+
+    it("Cranks an expired token escrow only through its own vault", async () => {
+      const program = anchor.workspace.PaymentEscrow as Program;
+
+      const mint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+      const payerToken = await tokenAccount(mint, payer.publicKey);
+      await mintTo(provider.connection, payer.payer, mint, payerToken, payer.publicKey, 1_000_000);
+
+      const { escrow, vault } = await createTokenEscrow(mint, new anchor.BN(1_000_000), {
+        expiresIn: 3,
+      });
+      await fundTokenEscrow(escrow, vault, mint, payerToken);
+      await sleep(5000);
+
+      const crank = (escrowVault: PublicKey) =>
+        program.methods
+          .crankExpired()
+          .accounts({ keeper: payer.publicKey, tokenProgram: TOKEN_PROGRAM_ID })
+          .remainingAccounts([
+            { pubkey: escrow, isSigner: false, isWritable: true },
+            { pubkey: payer.publicKey, isSigner: false, isWritable: true },
+            { pubkey: escrowVault, isSigner: false, isWritable: true },
+            { pubkey: payerToken, isSigner: false, isWritable: true },
+          ])
+          .rpc();
+
+      await expectError(crank(payerToken), "InvalidEscrowAccount");
+
+      await crank(vault);
+
+      assert.equal(
+        Number((await getAccount(provider.connection, payerToken)).amount),
+        1_000_000
+      );
+      assert.isNull(await provider.connection.getAccountInfo(vault));
+      assert.isNull(await program.account.escrow.fetchNullable(escrow));
+    });
+  });
+
+  describe("Payment Verification", () => {
+    let verifierAccount: PublicKey;
+    let paymentAccount: PublicKey;
+    let merchantStats: PublicKey;
+    const paymentProof = "TEST_PAYMENT_PROOF_" + Date.now();
+    const resourceHash = Buffer.from(
+      keccak_256("https://api.layerx402.com/premium/data")
+    );
+    const facilitator = Keypair.generate();
+
+    // `Network` variants in enum order, as encoded in facilitator attestations
+    const networks = [
+      "solana",
+      "solanaDevnet",
+      "base",
+      "baseSepolia",
+      "avalanche",
+      "avalancheFuji",
+      "polygon",
+      "polygonAmoy",
+    ];
+
+    // Records a facilitator-attested SOL payment to `recipient` and returns its PDAs and hash
+    async function recordPayment(
+      recipient: PublicKey,
+      options: {
+        amount?: anchor.BN;
+        signer?: Keypair;
+        nonce?: Buffer;
+        validAfter?: anchor.BN;
+        validBefore?: anchor.BN;
+        network?: string;
+        asset?: PublicKey;
+      } = {}
+    ) {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const amount = options.amount ?? new anchor.BN(1_000_000);
+      const signer = options.signer ?? facilitator;
+      const network = options.network ?? "solana";
+      const asset = options.asset ?? PublicKey.default;
+      const proof = `RECORDED_PROOF_${Date.now()}_${Math.random()}`;
+      const paymentHash = Buffer.from(keccak_256(proof));
+      const nonce = options.nonce ?? Buffer.from(Keypair.generate().publicKey.toBytes());
+      const requestHash = Buffer.alloc(32);
+      const now = Math.floor(Date.now() / 1000);
+      const validAfter = options.validAfter ?? new anchor.BN(now - 60);
+      const validBefore = options.validBefore ?? new anchor.BN(now + 600);
+
+      const [payment] = PublicKey.findProgramAddressSync(
+        [Buffer.from("payment"), paymentHash],
+        program.programId
+      );
+      const [stats] = PublicKey.findProgramAddressSync(
+        [Buffer.from("merchant"), recipient.toBuffer()],
+        program.programId
+      );
+      const [nonceRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nonce"), payer.publicKey.toBuffer(), nonce],
+        program.programId
+      );
+
+      const attestation = Buffer.concat([
+        paymentHash,
+        payer.publicKey.toBuffer(),
+        recipient.toBuffer(),
+        amount.toArrayLike(Buffer, "le", 8),
+        Buffer.from([networks.indexOf(network)]),
+        asset.toBuffer(),
+        validAfter.toArrayLike(Buffer, "le", 8),
+        validBefore.toArrayLike(Buffer, "le", 8),
+        nonce,
+        resourceHash,
+        requestHash,
+      ]);
+
+      await program.methods
+        .verifyPayment({
+          paymentHash: [...paymentHash],
+          paymentProof: proof,
+          amount,
+          recipient,
+          network: { [network]: {} },
+          asset,
+          transactionSignature: "recorded",
+          mode: { reported: {} },
+          validAfter,
+          validBefore,
+          nonce: [...nonce],
+          resourceHash: [...resourceHash],
+          requestHash: [...requestHash],
+          description: null,
+        })
+        .accounts({
+          payment,
+          verifier: verifierAccount,
+          payer: payer.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          payerToken: null,
+          recipientToken: null,
+          nonceRecord,
+          merchantStats: stats,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: signer.secretKey,
+            message: attestation,
+          }),
+        ])
+        .rpc();
+
+      return { payment, stats, paymentHash, proof };
+    }
+
+    it("Initializes the verifier", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      [verifierAccount] = PublicKey.findProgramAddressSync(
+        [Buffer.from("verifier")],
+        program.programId
+      );
+
+      await program.methods
+        .initializeVerifier()
+        .accounts({
+          verifier: verifierAccount,
+          authority: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const verifierData = await program.account.verifier.fetch(verifierAccount);
+      assert.ok(verifierData.authority.equals(payer.publicKey));
+      assert.ok(verifierData.totalVerifications.eq(new anchor.BN(0)));
+    });
+
+    it("Registers a facilitator", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      await program.methods
+        .addFacilitator(facilitator.publicKey)
+        .accounts({
+          verifier: verifierAccount,
+          authority: payer.publicKey,
+        })
+        .rpc();
+
+      const verifierData = await program.account.verifier.fetch(verifierAccount);
+      assert.ok(verifierData.facilitators[0].equals(facilitator.publicKey));
+    });
+
+    it("Configures a network", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      await program.methods
+        .configureNetwork(
+          { solana: {} },
+          true,
+          [PublicKey.default], // native SOL
+          new anchor.BN(1_000)
+        )
+        .accounts({
+          verifier: verifierAccount,
+          authority: payer.publicKey,
+        })
+        .rpc();
+
+      const verifierData = await program.account.verifier.fetch(verifierAccount);
+      assert.ok(verifierData.networks[0].network.solana);
+      assert.equal(verifierData.networks[0].enabled, true);
+    });
+
+    it("Verifies a payment", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const recipient = Keypair.generate().publicKey;
+      const amount = new anchor.BN(500_000_000); // 0.5 SOL
+
+      const paymentHash = Buffer.from(keccak_256(paymentProof));
+
+      [paymentAccount] = PublicKey.findProgramAddressSync(
+        [Buffer.from("payment"), paymentHash],
+        program.programId
+      );
+
+      const now = Math.floor(Date.now() / 1000);
+      const validAfter = new anchor.BN(now - 60);
+      const validBefore = new anchor.BN(now + 600);
+      const nonce = Buffer.from(Keypair.generate().publicKey.toBytes());
+
+      [merchantStats] = PublicKey.findProgramAddressSync(
+        [Buffer.from("merchant"), recipient.toBuffer()],
+        program.programId
+      );
+      const requestHash = Buffer.from(keccak_256("GET /premium/data"));
+
+      const [nonceRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nonce"), payer.publicKey.toBuffer(), nonce],
+        program.programId
+      );
+
+      const attestation = Buffer.concat([
+        paymentHash,
+        payer.publicKey.toBuffer(),
+        recipient.toBuffer(),
+        amount.toArrayLike(Buffer, "le", 8),
+        Buffer.from([0]), // Network::Solana
+        PublicKey.default.toBuffer(),
+        validAfter.toArrayLike(Buffer, "le", 8),
+        validBefore.toArrayLike(Buffer, "le", 8),
+        nonce,
+        resourceHash,
+        requestHash,
+      ]);
+
+      await program.methods
+        .verifyPayment({
+          paymentHash: [...paymentHash],
+          paymentProof,
+          amount,
+          recipient,
+          network: { solana: {} },
+          asset: PublicKey.default,
+          transactionSignature: "5wHu7J9VqYZN8xN9xN9xN9xN9xN9xN9xN9xN9xN9xN9x",
+          mode: { reported: {} },
+          validAfter,
+          validBefore,
+          nonce: [...nonce],
+          resourceHash: [...resourceHash],
+          requestHash: [...requestHash],
+          description: "Premium data feed",
+        })
+        .accounts({
+          payment: paymentAccount,
+          verifier: verifierAccount,
+          payer: payer.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          payerToken: null,
+          recipientToken: null,
+          nonceRecord,
+          merchantStats,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: facilitator.secretKey,
+            message: attestation,
+          }),
+        ])
+        .rpc();
+
+      const paymentData = await program.account.payment.fetch(paymentAccount);
+      assert.ok(paymentData.amount.eq(amount));
+      assert.ok(paymentData.recipient.equals(recipient));
+      assert.ok(paymentData.network.solana);
+      assert.equal(paymentData.status.verified, true);
+      assert.ok(paymentData.facilitator.equals(facilitator.publicKey));
+      assert.ok(paymentData.validBefore.eq(validBefore));
+      assert.ok(Buffer.from(paymentData.resourceHash).equals(resourceHash));
+
+      const verifierData = await program.account.verifier.fetch(verifierAccount);
+      assert.ok(verifierData.totalVerifications.eq(new anchor.BN(1)));
+
+      const statsData = await program.account.merchantStats.fetch(merchantStats);
+      assert.ok(statsData.verifiedCount.eq(new anchor.BN(1)));
+      assert.ok(statsData.verifiedVolume.eq(amount));
+    });
+
+    it("Rotates and removes facilitators and rejects unregistered signers", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+      const accounts = { verifier: verifierAccount, authority: payer.publicKey };
+
+      const unregistered = Keypair.generate();
+      await expectError(
+        recordPayment(Keypair.generate().publicKey, { signer: unregistered }),
+        "UnknownFacilitator"
+      );
+
+      // A rotated-out key stops attesting; its replacement takes its slot
+      const rotated = Keypair.generate();
+      await program.methods
+        .rotateFacilitator(facilitator.publicKey, rotated.publicKey)
+        .accounts(accounts)
+        .rpc();
+
+      let verifierData = await program.account.verifier.fetch(verifierAccount);
+      assert.ok(verifierData.facilitators[0].equals(rotated.publicKey));
+      await expectError(recordPayment(Keypair.generate().publicKey), "UnknownFacilitator");
+
+      const { payment } = await recordPayment(Keypair.generate().publicKey, { signer: rotated });
+      const paymentData = await program.account.payment.fetch(payment);
+      assert.ok(paymentData.facilitator.equals(rotated.publicKey));
+
+      await expectError(
+        program.methods
+          .rotateFacilitator(unregistered.publicKey, Keypair.generate().publicKey)
+          .accounts(accounts)
+          .rpc(),
+        "UnknownFacilitator"
+      );
+
+      // A removed key no longer attests, and cannot be removed twice
+      await program.methods.removeFacilitator(rotated.publicKey).accounts(accounts).rpc();
+
+      verifierData = await program.account.verifier.fetch(verifierAccount);
+      assert.equal(
+        verifierData.facilitators.filter((key: PublicKey) => key.equals(rotated.publicKey)).length,
+        0
+      );
+      await expectError(
+        recordPayment(Keypair.generate().publicKey, { signer: rotated }),
+        "UnknownFacilitator"
+      );
+      await expectError(
+        program.methods.removeFacilitator(rotated.publicKey).accounts(accounts).rpc(),
+        "UnknownFacilitator"
+      );
+
+      // Restore the facilitator the remaining tests attest with
+      await program.methods.addFacilitator(facilitator.publicKey).accounts(accounts).rpc();
+    });
+
+    it("Rejects replayed nonces and payments outside their validity window", async () => {
+      const recipient = Keypair.generate().publicKey;
+      const now = Math.floor(Date.now() / 1000);
+
+      // Each x402 authorization nonce records at most one payment per payer
+      const nonce = Buffer.from(Keypair.generate().publicKey.toBytes());
+      await recordPayment(recipient, { nonce });
+      await expectError(recordPayment(recipient, { nonce }), "NonceAlreadyUsed");
+
+      await expectError(
+        recordPayment(recipient, {
+          validAfter: new anchor.BN(now - 600),
+          validBefore: new anchor.BN(now - 60),
+        }),
+        "PaymentExpired"
+      );
+      await expectError(
+        recordPayment(recipient, {
+          validAfter: new anchor.BN(now + 600),
+          validBefore: new anchor.BN(now + 1200),
+        }),
+        "PaymentNotYetValid"
+      );
+      await expectError(
+        recordPayment(recipient, {
+          validAfter: new anchor.BN(now + 600),
+          validBefore: new anchor.BN(now - 600),
+        }),
+        "InvalidValidityWindow"
+      );
+    });
+
+    it("Rejects payments on unconfigured or disabled networks and in unaccepted terms", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+      const recipient = Keypair.generate().publicKey;
+
+      await expectError(
+        recordPayment(recipient, { network: "polygonAmoy" }),
+        "UnsupportedNetwork"
+      );
+
+      await program.methods
+        .configureNetwork({ baseSepolia: {} }, false, [PublicKey.default], new anchor.BN(0))
+        .accounts({ verifier: verifierAccount, authority: payer.publicKey })
+        .rpc();
+      await expectError(
+        recordPayment(recipient, { network: "baseSepolia" }),
+        "NetworkDisabled"
+      );
+
+      // Solana is configured for native SOL only, with a 1_000 lamport minimum
+      await expectError(
+        recordPayment(recipient, { asset: Keypair.generate().publicKey }),
+        "UnsupportedAsset"
+      );
+      await expectError(
+        recordPayment(recipient, { amount: new anchor.BN(999) }),
+        "AmountBelowMinimum"
+      );
+    });
+
+    it("Verifies an atomic payment only against the payer's own transfer", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const recipient = Keypair.generate().publicKey;
+      const amount = new anchor.BN(1_000_000);
+      const proof = "ATOMIC_PAYMENT_PROOF_" + Date.now();
+      const paymentHash = Buffer.from(keccak_256(proof));
+      const nonce = Buffer.from(Keypair.generate().publicKey.toBytes());
+      const now = Math.floor(Date.now() / 1000);
+
+      const [payment] = PublicKey.findProgramAddressSync(
+        [Buffer.from("payment"), paymentHash],
+        program.programId
+      );
+      const [nonceRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nonce"), payer.publicKey.toBuffer(), nonce],
+        program.programId
+      );
+      const [atomicStats] = PublicKey.findProgramAddressSync(
+        [Buffer.from("merchant"), recipient.toBuffer()],
+        program.programId
+      );
+
+      const verify = (from: PublicKey, signers: Keypair[]) =>
+        program.methods
+          .verifyPayment({
+            paymentHash: [...paymentHash],
+            paymentProof: proof,
+            amount,
+            recipient,
+            network: { solana: {} },
+            asset: PublicKey.default,
+            transactionSignature: "atomic",
+            mode: { atomic: {} },
+            validAfter: new anchor.BN(now - 60),
+            validBefore: new anchor.BN(now + 600),
+            nonce: [...nonce],
+            resourceHash: [...resourceHash],
+            requestHash: [...Buffer.alloc(32)],
+            description: null,
+          })
+          .accounts({
+            payment,
+            verifier: verifierAccount,
+            payer: payer.publicKey,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            payerToken: null,
+            recipientToken: null,
+            nonceRecord,
+            merchantStats: atomicStats,
+            systemProgram: SystemProgram.programId,
+          })
+          .preInstructions([
+            SystemProgram.transfer({
+              fromPubkey: from,
+              toPubkey: recipient,
+              lamports: amount.toNumber(),
+            }),
+          ])
+          .signers(signers)
+          .rpc();
+
+      // Someone else's transfer to the recipient does not back this payer's payment
+      const stranger = Keypair.generate();
+      const airdrop = await provider.connection.requestAirdrop(
+        stranger.publicKey,
+        100_000_000
+      );
+      await provider.connection.confirmTransaction(airdrop);
+      await expectError(verify(stranger.publicKey, [stranger]), "TransferNotFound");
+
+      await verify(payer.publicKey, []);
+
+      const paymentData = await program.account.payment.fetch(payment);
+      assert.ok(paymentData.verificationMode.atomic);
+      assert.equal(paymentData.status.verified, true);
+    });
+
+    it("Grants access to the paid resource", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const granted = await program.methods
+        .checkResourceAccess(payer.publicKey, [...resourceHash], new anchor.BN(3600))
+        .accounts({ payment: paymentAccount })
+        .view();
+
+      assert.equal(granted, true);
+    });
+
+    it("Releases a verification-gated escrow only against a matching Verified payment", async () => {
+      const escrowProgram = anchor.workspace.PaymentEscrow as Program;
+
+      const recipient = Keypair.generate().publicKey;
+      const escrowAmount = new anchor.BN(1_000_000);
+      const { payment, paymentHash, proof } = await recordPayment(recipient, { amount: escrowAmount });
+
+      const otherPayer = Keypair.generate();
+      const airdrop = await provider.connection.requestAirdrop(
+        otherPayer.publicKey,
+        100_000_000
+      );
+      await provider.connection.confirmTransaction(airdrop);
+
+      const mint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+      const payerToken = (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          payer.payer,
+          mint,
+          payer.publicKey
+        )
+      ).address;
+      await mintTo(provider.connection, payer.payer, mint, payerToken, payer.publicKey, 1_000_000);
+
+      // Creates and funds an escrow committed to `paymentHash` that requires a verified payment
+      async function createGatedEscrow(
+        escrowPayer: Keypair,
+        escrowRecipient: PublicKey,
+        escrowMint: PublicKey | null = null
+      ): Promise<PublicKey> {
+        const id = new anchor.BN(Date.now() + Math.floor(Math.random() * 1000));
+        const [escrow] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("escrow"),
+            escrowPayer.publicKey.toBuffer(),
+            id.toArrayLike(Buffer, "le", 8),
+          ],
+          escrowProgram.programId
+        );
+
+        await escrowProgram.methods
+          .initializeEscrow(id, {
+            amount: escrowAmount,
+            recipient: escrowRecipient,
+            expiryTimestamp: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+            mint: escrowMint,
+            proofHash: [...paymentHash],
+            milestones: [],
+            arbiter: null,
+            facilitator: null,
+            keeperTip: new anchor.BN(0),
+            recipients: [],
+            requireVerifiedPayment: true,
+          })
+          .accounts({
+            escrow,
+            payer: escrowPayer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([escrowPayer])
+          .rpc();
+
+        if (escrowMint) {
+          const [vault] = PublicKey.findProgramAddressSync(
+            [Buffer.from("vault"), escrow.toBuffer()],
+            escrowProgram.programId
+          );
+          await escrowProgram.methods
+            .initializeEscrowVault()
+            .accounts({
+              escrow,
+              mint: escrowMint,
+              vault,
+              payer: escrowPayer.publicKey,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+              rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            })
+            .signers([escrowPayer])
+            .rpc();
+          await escrowProgram.methods
+            .fundEscrow(escrowAmount)
+            .accounts({
+              escrow,
+              payer: escrowPayer.publicKey,
+              payerToken,
+              vault,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([escrowPayer])
+            .rpc();
+        } else {
+          await escrowProgram.methods
+            .fundEscrow(escrowAmount)
+            .accounts({
+              escrow,
+              payer: escrowPayer.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([escrowPayer])
+            .rpc();
+        }
+
+        return escrow;
+      }
+
+      const release = (
+        escrow: PublicKey,
+        escrowRecipient: PublicKey,
+        verifiedPayment: PublicKey | null
+      ) =>
+        escrowProgram.methods
+          .releaseEscrow(proof)
+          .accounts({
+            escrow,
+            recipient: escrowRecipient,
+            verifiedPayment,
+            authority: payer.publicKey,
+          })
+          .rpc();
+
+      const escrow = await createGatedEscrow(payer.payer, recipient);
+      await expectError(release(escrow, recipient, null), "PaymentNotVerified");
+
+      // The record must describe this escrow's payer, recipient and asset
+      const otherRecipient = Keypair.generate().publicKey;
+      const wrongPayer = await createGatedEscrow(otherPayer, recipient);
+      const wrongRecipient = await createGatedEscrow(payer.payer, otherRecipient);
+      const wrongAsset = await createGatedEscrow(payer.payer, recipient, mint);
+      await expectError(release(wrongPayer, recipient, payment), "PaymentMismatch");
+      await expectError(release(wrongRecipient, otherRecipient, payment), "PaymentMismatch");
+      await expectError(release(wrongAsset, recipient, payment), "PaymentMismatch");
+
+      const recipientBefore = await provider.connection.getBalance(recipient);
+      await release(escrow, recipient, payment);
+      const recipientAfter = await provider.connection.getBalance(recipient);
+
+      assert.equal(recipientAfter - recipientBefore, escrowAmount.toNumber());
+      const escrowData = await escrowProgram.account.escrow.fetch(escrow);
+      assert.equal(escrowData.status.released, true);
+    });
+
+    it("Settles a payment", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      await program.methods
+        .settlePayment()
+        .accounts({
+          payment: paymentAccount,
+          verifier: verifierAccount,
+          authority: payer.publicKey,
+          merchantStats,
+        })
+        .rpc();
+
+      const paymentData = await program.account.payment.fetch(paymentAccount);
+      assert.equal(paymentData.status.settled, true);
+      assert.ok(paymentData.settledAt !== null);
+
+      const statsData = await program.account.merchantStats.fetch(merchantStats);
+      assert.ok(statsData.settledCount.eq(new anchor.BN(1)));
+    });
+
+    it("Proves a batched payment against the batch root", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const recipient = Keypair.generate().publicKey;
+      const amount = new anchor.BN(250_000);
+      const receipt = (hash: Buffer) =>
+        Buffer.from(
+          keccak_256(
+            Buffer.concat([
+              Buffer.from([0]),
+              hash,
+              payer.publicKey.toBuffer(),
+              recipient.toBuffer(),
+              amount.toArrayLike(Buffer, "le", 8),
+              PublicKey.default.toBuffer(),
+              resourceHash,
+            ])
+          )
+        );
+
+      const hashes = [0, 1].map((i) =>
+        Buffer.from(keccak_256(`BATCHED_PROOF_${i}_${Date.now()}`))
+      );
+      const leaves = hashes.map(receipt);
+      const [low, high] =
+        Buffer.compare(leaves[0], leaves[1]) <= 0 ? leaves : [leaves[1], leaves[0]];
+      const merkleRoot = Buffer.from(
+        keccak_256(Buffer.concat([Buffer.from([1]), low, high]))
+      );
+
+      const [batch] = PublicKey.findProgramAddressSync(
+        [Buffer.from("batch"), merkleRoot],
+        program.programId
+      );
+      const [payment] = PublicKey.findProgramAddressSync(
+        [Buffer.from("payment"), hashes[0]],
+        program.programId
+      );
+      const [batchStats] = PublicKey.findProgramAddressSync(
+        [Buffer.from("merchant"), recipient.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .verifyPaymentBatch([...merkleRoot], { solana: {} }, 2, amount.muln(2))
+        .accounts({
+          batch,
+          verifier: verifierAccount,
+          submitter: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      // Declared batch totals do not count toward verifier stats
+      const before = await program.account.verifier.fetch(verifierAccount);
+
+      const prove = (sibling: Buffer) =>
+        program.methods
+          .provePayment({
+            paymentHash: [...hashes[0]],
+            payer: payer.publicKey,
+            recipient,
+            amount,
+            asset: PublicKey.default,
+            resourceHash: [...resourceHash],
+            proof: [[...sibling]],
+          })
+          .accounts({
+            payment,
+            batch,
+            verifier: verifierAccount,
+            merchantStats: batchStats,
+            prover: payer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+      await expectError(prove(hashes[1]), "InvalidMerkleProof");
+
+      // Receipts are held to the network's terms at the time they are proven
+      const configureSolana = (enabled: boolean, assets: PublicKey[], minAmount: number) =>
+        program.methods
+          .configureNetwork({ solana: {} }, enabled, assets, new anchor.BN(minAmount))
+          .accounts({ verifier: verifierAccount, authority: payer.publicKey })
+          .rpc();
+
+      await configureSolana(false, [PublicKey.default], 1_000);
+      await expectError(prove(leaves[1]), "NetworkDisabled");
+      await configureSolana(true, [Keypair.generate().publicKey], 1_000);
+      await expectError(prove(leaves[1]), "UnsupportedAsset");
+      await configureSolana(true, [PublicKey.default], 500_000);
+      await expectError(prove(leaves[1]), "AmountBelowMinimum");
+      await configureSolana(true, [PublicKey.default], 1_000);
+
+      await prove(leaves[1]);
+
+      const paymentData = await program.account.payment.fetch(payment);
+      assert.equal(paymentData.status.verified, true);
+      assert.ok(paymentData.batch.equals(batch));
+
+      const after = await program.account.verifier.fetch(verifierAccount);
+      assert.ok(
+        after.totalVerifications.eq(before.totalVerifications.addn(1))
+      );
+      assert.ok(after.totalVolume.eq(before.totalVolume.add(amount)));
+
+      const batchData = await program.account.paymentBatch.fetch(batch);
+      assert.equal(batchData.provenCount, 1);
+    });
+
+    it("Closes the dispute window and applies the default resolution", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const setDisputeConfig = (window: number, deadline: number, resolution: object) =>
+        program.methods
+          .updateDisputeConfig(new anchor.BN(window), new anchor.BN(deadline), resolution)
+          .accounts({ verifier: verifierAccount, authority: payer.publicKey })
+          .rpc();
+
+      const dispute = (payment: PublicKey, stats: PublicKey) =>
+        program.methods
+          .disputePayment("Service not delivered")
+          .accounts({
+            payment,
+            verifier: verifierAccount,
+            disputer: payer.publicKey,
+            merchantStats: stats,
+          })
+          .rpc();
+
+      const applyDefault = (payment: PublicKey, stats: PublicKey) =>
+        program.methods
+          .applyDefaultResolution()
+          .accounts({ payment, verifier: verifierAccount, merchantStats: stats })
+          .rpc();
+
+      await setDisputeConfig(2, 2, { refundPayer: {} });
+
+      const recipient = Keypair.generate().publicKey;
+      const open = await recordPayment(recipient);
+      const late = await recordPayment(recipient);
+
+      await dispute(open.payment, open.stats);
+      await expectError(
+        applyDefault(open.payment, open.stats),
+        "ResolutionDeadlineNotReached"
+      );
+
+      await sleep(4000);
+
+      // The window has passed for the undisputed payment
+      await expectError(dispute(late.payment, late.stats), "DisputeWindowClosed");
+
+      // and the disputed one falls back to the default resolution
+      await applyDefault(open.payment, open.stats);
+
+      const paymentData = await program.account.payment.fetch(open.payment);
+      assert.equal(paymentData.status.refunded, true);
+      assert.ok(paymentData.resolvedAt !== null);
+
+      const statsData = await program.account.merchantStats.fetch(open.stats);
+      assert.ok(statsData.refundedCount.eq(new anchor.BN(1)));
+
+      await setDisputeConfig(7 * 24 * 60 * 60, 14 * 24 * 60 * 60, { approvePayment: {} });
+    });
+
+    it("Does not count an approved dispute of a settled payment twice", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const amount = new anchor.BN(2_000_000);
+      const { payment, stats } = await recordPayment(Keypair.generate().publicKey, { amount });
+      const accounts = {
+        payment,
+        verifier: verifierAccount,
+        merchantStats: stats,
+      };
+
+      await program.methods
+        .settlePayment()
+        .accounts({ ...accounts, authority: payer.publicKey })
+        .rpc();
+      await program.methods
+        .disputePayment("Chargeback request")
+        .accounts({ ...accounts, disputer: payer.publicKey })
+        .rpc();
+      await program.methods
+        .resolveDispute({ approvePayment: {} })
+        .accounts({ ...accounts, authority: payer.publicKey })
+        .rpc();
+
+      const paymentData = await program.account.payment.fetch(payment);
+      assert.equal(paymentData.status.settled, true);
+
+      const statsData = await program.account.merchantStats.fetch(stats);
+      assert.ok(statsData.settledCount.eq(new anchor.BN(1)));
+      assert.ok(statsData.settledVolume.eq(amount));
+    });
+
+    it("Verifies a payment once a committee quorum attests", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const [memberA, memberB, memberC, outsider] = [0, 1, 2, 3].map(() =>
+        Keypair.generate()
+      );
+      for (const member of [memberA, memberB, memberC, outsider]) {
+        const airdrop = await provider.connection.requestAirdrop(
+          member.publicKey,
+          100_000_000
+        );
+        await provider.connection.confirmTransaction(airdrop);
+      }
+
+      const configureCommittee = (members: Keypair[], quorum: number) =>
+        program.methods
+          .configureCommittee(
+            members.map((member) => member.publicKey),
+            quorum
+          )
+          .accounts({ verifier: verifierAccount, authority: payer.publicKey })
+          .rpc();
+
+      const attest = (
+        recorded: { payment: PublicKey; stats: PublicKey; paymentHash: Buffer },
+        member: Keypair
+      ) => {
+        const [attestations] = PublicKey.findProgramAddressSync(
+          [Buffer.from("attestations"), recorded.paymentHash],
+          program.programId
+        );
+        return program.methods
+          .attestPayment([...recorded.paymentHash])
+          .accounts({
+            payment: recorded.payment,
+            attestations,
+            verifier: verifierAccount,
+            merchantStats: recorded.stats,
+            member: member.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([member])
+          .rpc();
+      };
+
+      await configureCommittee([memberA, memberB], 2);
+
+      const recorded = await recordPayment(Keypair.generate().publicKey);
+      let paymentData = await program.account.payment.fetch(recorded.payment);
+      assert.equal(paymentData.status.pending, true);
+
+      await expectError(attest(recorded, outsider), "NotCommitteeMember");
+      await attest(recorded, memberA);
+      await expectError(attest(recorded, memberA), "AlreadyAttested");
+
+      paymentData = await program.account.payment.fetch(recorded.payment);
+      assert.equal(paymentData.status.pending, true);
+
+      await attest(recorded, memberB);
+      paymentData = await program.account.payment.fetch(recorded.payment);
+      assert.equal(paymentData.status.verified, true);
+
+      // An attestation from a member rotated out no longer counts toward quorum
+      const rotated = await recordPayment(Keypair.generate().publicKey);
+      await attest(rotated, memberA);
+      await configureCommittee([memberB, memberC], 2);
+      await attest(rotated, memberB);
+
+      paymentData = await program.account.payment.fetch(rotated.payment);
+      assert.equal(paymentData.status.pending, true);
+
+      await attest(rotated, memberC);
+      paymentData = await program.account.payment.fetch(rotated.payment);
+      assert.equal(paymentData.status.verified, true);
+
+      await configureCommittee([], 0);
+    });
+
+    it("Finalizes pending payments left behind by a committee change", async () => {
+      const program = anchor.workspace.PaymentVerification as Program;
+
+      const [memberA, memberB, memberC] = [0, 1, 2].map(() => Keypair.generate());
+      for (const member of [memberA, memberB]) {
+        const airdrop = await provider.connection.requestAirdrop(
+          member.publicKey,
+          100_000_000
+        );
+        await provider.connection.confirmTransaction(airdrop);
+      }
+
+      const configureCommittee = (members: Keypair[], quorum: number) =>
+        program.methods
+          .configureCommittee(
+            members.map((member) => member.publicKey),
+            quorum
+          )
+          .accounts({ verifier: verifierAccount, authority: payer.publicKey })
+          .rpc();
+
+      const attestationsFor = (paymentHash: Buffer) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("attestations"), paymentHash],
+          program.programId
+        )[0];
+
+      const finalize = (
+        recorded: { payment: PublicKey; stats: PublicKey },
+        attestations: PublicKey | null
+      ) =>
+        program.methods
+          .finalizeAttestations()
+          .accounts({
+            payment: recorded.payment,
+            attestations,
+            verifier: verifierAccount,
+            merchantStats: recorded.stats,
+          })
+          .rpc();
+
+      // Every current member has attested, but under the old quorum of 3
+      await configureCommittee([memberA, memberB, memberC], 3);
+      const shrunk = await recordPayment(Keypair.generate().publicKey);
+      const attestations = attestationsFor(shrunk.paymentHash);
+      for (const member of [memberA, memberB]) {
+        await program.methods
+          .attestPayment([...shrunk.paymentHash])
+          .accounts({
+            payment: shrunk.payment,
+            attestations,
+            verifier: verifierAccount,
+            merchantStats: shrunk.stats,
+            member: member.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([member])
+          .rpc();
+      }
+      await expectError(finalize(shrunk, attestations), "QuorumNotReached");
+
+      await configureCommittee([memberA, memberB], 2);
+      await finalize(shrunk, attestations);
+
+      let paymentData = await program.account.payment.fetch(shrunk.payment);
+      assert.equal(paymentData.status.verified, true);
+      await expectError(finalize(shrunk, attestations), "InvalidPaymentStatus");
+
+      // Clearing the committee releases payments nobody attested
+      const unattested = await recordPayment(Keypair.generate().publicKey);
+      await configureCommittee([], 0);
+
+      const statsBefore = await program.account.merchantStats.fetch(unattested.stats);
+      await finalize(unattested, null);
+
+      paymentData = await program.account.payment.fetch(unattested.payment);
+      assert.equal(paymentData.status.verified, true);
+      const statsAfter = await program.account.merchantStats.fetch(unattested.stats);
+      assert.ok(statsAfter.verifiedCount.eq(statsBefore.verifiedCount.addn(1)));
+    });
+  });
+
+  describe("Payment Settlement", () => {
+    let poolAccount: PublicKey;
+    let settlementAccount: PublicKey;
+    let mint: PublicKey;
+    let poolVault: PublicKey;
+    let mintFees: PublicKey;
+    const paymentId = "PAYMENT_" + Date.now();
+
+    // Rolling volume PDA of `recipient` in `mint` (SOL when null)
+    function merchantVolumeFor(recipient: PublicKey, mint: PublicKey | null = null) {
+      const program = anchor.workspace.PaymentSettlement as Program;
+      return PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("merchant_volume"),
+          recipient.toBuffer(),
+          (mint ?? PublicKey.default).toBuffer(),
+        ],
+        program.programId
+      )[0];
+    }
+
+    function feeScheduleFor(mint: PublicKey | null = null) {
+      const program = anchor.workspace.PaymentSettlement as Program;
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("fee_schedule"), (mint ?? PublicKey.default).toBuffer()],
+        program.programId
+      )[0];
+    }
+
+    // Sum of a merchant volume account's daily buckets
+    const totalVolume = (volume: { dailyVolume: anchor.BN[] }) =>
+      volume.dailyVolume.reduce((sum, day) => sum.add(day), new anchor.BN(0));
+
+    // Creates a pending settlement from `payer` to `recipient` and returns its PDA
+    async function createSettlement(
+      recipient: PublicKey,
+      amount: anchor.BN,
+      mint: PublicKey | null = null
+    ) {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const id = `PAYMENT_${Date.now()}_${Math.floor(Math.random() * 1e6)}`;
+      const [settlement] = PublicKey.findProgramAddressSync(
+        [Buffer.from("settlement"), payer.publicKey.toBuffer(), Buffer.from(id)],
+        program.programId
+      );
+      const [feeOverride] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee_override"), recipient.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .createSettlement(amount, recipient, id, mint)
+        .accounts({
+          settlement,
+          pool: poolAccount,
+          feeSchedule: feeScheduleFor(mint),
+          feeOverride,
+          merchantVolume: merchantVolumeFor(recipient, mint),
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      return settlement;
+    }
+
+    it("Initializes the settlement pool", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      [poolAccount] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pool")],
+        program.programId
+      );
+
+      const feePercentage = 100; // 1%
+
+      await program.methods
+        .initializePool(feePercentage)
+        .accounts({
+          pool: poolAccount,
+          authority: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const poolData = await program.account.settlementPool.fetch(poolAccount);
+      assert.ok(poolData.authority.equals(payer.publicKey));
+      assert.equal(poolData.feePercentage, feePercentage);
+      assert.ok(poolData.totalSettled.eq(new anchor.BN(0)));
+    });
+
+    it("Creates a settlement", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const recipient = Keypair.generate().publicKey;
+      const amount = new anchor.BN(1_000_000_000); // 1 SOL
+
+      [settlementAccount] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("settlement"),
+          payer.publicKey.toBuffer(),
+          Buffer.from(paymentId),
+        ],
+        program.programId
+      );
+
+      const feeSchedule = feeScheduleFor(null);
+      const [feeOverride] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee_override"), recipient.toBuffer()],
+        program.programId
+      );
+      const merchantVolume = merchantVolumeFor(recipient);
+
+      await program.methods
+        .createSettlement(amount, recipient, paymentId, null)
+        .accounts({
+          settlement: settlementAccount,
+          pool: poolAccount,
+          feeSchedule,
+          feeOverride,
+          merchantVolume,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const settlementData = await program.account.settlement.fetch(
+        settlementAccount
+      );
+      assert.ok(settlementData.grossAmount.eq(amount));
+      assert.equal(settlementData.paymentId, paymentId);
+      assert.equal(settlementData.status.pending, true);
+
+      // Check fee calculation (1%, no schedule or override configured)
+      const expectedFee = amount.muln(1).divn(100);
+      assert.ok(settlementData.feeAmount.eq(expectedFee));
+      assert.equal(settlementData.feeBps, 100);
+      assert.ok(settlementData.feeSource.flat);
+
+      // Volume only counts once the settlement is processed
+      const volumeData = await program.account.merchantVolume.fetch(merchantVolume);
+      assert.ok(totalVolume(volumeData).eqn(0));
+    });
+
+    it("Processes a settlement", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const settlementData = await program.account.settlement.fetch(
+        settlementAccount
+      );
+      const recipient = settlementData.recipient;
+
+      const recipientBalanceBefore = await provider.connection.getBalance(
+        recipient
+      );
+
+      await program.methods
+        .processSettlement()
+        .accounts({
+          settlement: settlementAccount,
+          pool: poolAccount,
+          payer: payer.publicKey,
+          recipient: recipient,
+          payerToken: null,
+          poolVault: null,
+          recipientToken: null,
+          mintFees: null,
+          merchantVolume: merchantVolumeFor(recipient),
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const volumeData = await program.account.merchantVolume.fetch(
+        merchantVolumeFor(recipient)
+      );
+      assert.ok(totalVolume(volumeData).eq(settlementData.grossAmount));
+
+      const recipientBalanceAfter = await provider.connection.getBalance(
         recipient
       );
 
@@ -281,6 +2052,379 @@ describe("Layerx402 Smart Contracts", () => {
       assert.ok(poolData.totalFeesCollected.gt(new anchor.BN(0)));
     });
 
+    it("Fails a whole all-or-nothing batch on one invalid entry", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const amount = new anchor.BN(10_000_000);
+      const recipients = [Keypair.generate().publicKey, Keypair.generate().publicKey];
+      const settlements = await Promise.all(
+        recipients.map((recipient) => createSettlement(recipient, amount))
+      );
+
+      const batch = (entries: PublicKey[]) =>
+        program.methods
+          .batchProcessSettlements(entries.length / 3, { allOrNothing: {} })
+          .accounts({
+            pool: poolAccount,
+            payer: payer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts(
+            entries.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+          )
+          .rpc();
+
+      // The second entry pays the wrong recipient, so nothing is processed
+      await expectError(
+        batch([
+          settlements[0], recipients[0], merchantVolumeFor(recipients[0]),
+          settlements[1], recipients[0], merchantVolumeFor(recipients[1]),
+        ]),
+        "InvalidRecipient"
+      );
+      for (const settlement of settlements) {
+        const data = await program.account.settlement.fetch(settlement);
+        assert.equal(data.status.pending, true);
+      }
+
+      await batch([
+        settlements[0], recipients[0], merchantVolumeFor(recipients[0]),
+        settlements[1], recipients[1], merchantVolumeFor(recipients[1]),
+      ]);
+      for (const settlement of settlements) {
+        const data = await program.account.settlement.fetch(settlement);
+        assert.equal(data.status.completed, true);
+      }
+      for (const recipient of recipients) {
+        const volume = await program.account.merchantVolume.fetch(merchantVolumeFor(recipient));
+        assert.ok(totalVolume(volume).eq(amount));
+      }
+    });
+
+    it("Skips invalid entries in a skip-invalid batch", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const amount = new anchor.BN(10_000_000);
+      const recipient = Keypair.generate().publicKey;
+      const valid = await createSettlement(recipient, amount);
+
+      // `settlementAccount` was already processed above
+      const settled = await program.account.settlement.fetch(settlementAccount);
+
+      const poolBefore = await program.account.settlementPool.fetch(poolAccount);
+
+      await program.methods
+        .batchProcessSettlements(2, { skipInvalid: {} })
+        .accounts({
+          pool: poolAccount,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          [
+            settlementAccount,
+            settled.recipient,
+            merchantVolumeFor(settled.recipient),
+            valid,
+            recipient,
+            merchantVolumeFor(recipient),
+          ].map((pubkey) => ({
+            pubkey,
+            isSigner: false,
+            isWritable: true,
+          }))
+        )
+        .rpc();
+
+      const validData = await program.account.settlement.fetch(valid);
+      assert.equal(validData.status.completed, true);
+      assert.equal(await provider.connection.getBalance(recipient), validData.netAmount.toNumber());
+
+      const poolAfter = await program.account.settlementPool.fetch(poolAccount);
+      assert.ok(poolAfter.totalSettled.eq(poolBefore.totalSettled.add(validData.netAmount)));
+    });
+
+    it("Processes a token settlement through the mint's pool vault", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      mint = await createMint(provider.connection, payer.payer, payer.publicKey, null, 6);
+      [poolVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pool_vault"), mint.toBuffer()],
+        program.programId
+      );
+      [mintFees] = PublicKey.findProgramAddressSync(
+        [Buffer.from("mint_fees"), mint.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .initializeMintVault()
+        .accounts({
+          pool: poolAccount,
+          mint,
+          poolVault,
+          mintFees,
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+
+      const recipient = Keypair.generate().publicKey;
+      const payerToken = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        mint,
+        payer.publicKey
+      );
+      const recipientToken = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        mint,
+        recipient
+      );
+      await mintTo(provider.connection, payer.payer, mint, payerToken.address, payer.publicKey, 10_000_000);
+
+      const amount = new anchor.BN(1_000_000);
+      const settlement = await createSettlement(recipient, amount, mint);
+
+      const process = (vault: PublicKey) =>
+        program.methods
+          .processSettlement()
+          .accounts({
+            settlement,
+            pool: poolAccount,
+            payer: payer.publicKey,
+            recipient,
+            payerToken: payerToken.address,
+            poolVault: vault,
+            recipientToken: recipientToken.address,
+            mintFees,
+            merchantVolume: merchantVolumeFor(recipient, mint),
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+      // Only the mint's pool vault PDA is accepted
+      await expectError(process(recipientToken.address), "ConstraintSeeds");
+
+      await process(poolVault);
+
+      const settlementData = await program.account.settlement.fetch(settlement);
+      assert.equal(settlementData.status.completed, true);
+
+      const received = await getAccount(provider.connection, recipientToken.address);
+      assert.equal(Number(received.amount), settlementData.netAmount.toNumber());
+
+      const vault = await getAccount(provider.connection, poolVault);
+      assert.equal(Number(vault.amount), settlementData.feeAmount.toNumber());
+
+      const mintFeesData = await program.account.mintFees.fetch(mintFees);
+      assert.ok(mintFeesData.totalFeesCollected.eq(settlementData.feeAmount));
+    });
+
+    it("Caps fee withdrawals at the fees not yet withdrawn", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const poolData = await program.account.settlementPool.fetch(poolAccount);
+      const available = poolData.totalFeesCollected.sub(poolData.totalFeesWithdrawn);
+
+      await expectError(
+        program.methods
+          .withdrawFees(available.addn(1), null)
+          .accounts({
+            pool: poolAccount,
+            authority: payer.publicKey,
+            feeDestination: null,
+            mintFees: null,
+            poolVault: null,
+            destinationToken: null,
+            tokenProgram: null,
+          })
+          .rpc(),
+        "ExceedsAvailableFees"
+      );
+    });
+
+    it("Withdraws fees only to the fee destination and keeps the pool rent-exempt", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const destination = Keypair.generate().publicKey;
+      const setDestination = (feeDestination: PublicKey | null) =>
+        program.methods
+          .setFeeDestination(feeDestination)
+          .accounts({ pool: poolAccount, authority: payer.publicKey })
+          .rpc();
+      const withdraw = (amount: anchor.BN, feeDestination: PublicKey | null) =>
+        program.methods
+          .withdrawFees(amount, null)
+          .accounts({
+            pool: poolAccount,
+            authority: payer.publicKey,
+            feeDestination,
+            mintFees: null,
+            poolVault: null,
+            destinationToken: null,
+            tokenProgram: null,
+          })
+          .rpc();
+
+      await setDestination(destination);
+
+      const poolData = await program.account.settlementPool.fetch(poolAccount);
+      const available = poolData.totalFeesCollected.sub(poolData.totalFeesWithdrawn);
+
+      // Neither the authority nor another account may receive the fees
+      await expectError(withdraw(available, null), "InvalidFeeDestination");
+      await expectError(
+        withdraw(available, Keypair.generate().publicKey),
+        "InvalidFeeDestination"
+      );
+
+      await withdraw(available, destination);
+
+      assert.equal(await provider.connection.getBalance(destination), available.toNumber());
+
+      // Withdrawing every collected fee leaves exactly the rent-exempt reserve
+      const poolInfo = await provider.connection.getAccountInfo(poolAccount);
+      const rentExempt = await provider.connection.getMinimumBalanceForRentExemption(
+        poolInfo.data.length
+      );
+      assert.equal(poolInfo.lamports, rentExempt);
+
+      await setDestination(null);
+    });
+
+    it("Withdraws token fees from the mint's pool vault", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const destinationToken = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer.payer,
+        mint,
+        payer.publicKey
+      );
+      const mintFeesData = await program.account.mintFees.fetch(mintFees);
+      const available = mintFeesData.totalFeesCollected.sub(
+        mintFeesData.totalFeesWithdrawn
+      );
+      const before = await getAccount(provider.connection, destinationToken.address);
+
+      const withdraw = (amount: anchor.BN) =>
+        program.methods
+          .withdrawFees(amount, mint)
+          .accounts({
+            pool: poolAccount,
+            authority: payer.publicKey,
+            feeDestination: null,
+            mintFees,
+            poolVault,
+            destinationToken: destinationToken.address,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+
+      await expectError(withdraw(available.addn(1)), "ExceedsAvailableFees");
+      await withdraw(available);
+
+      const after = await getAccount(provider.connection, destinationToken.address);
+      assert.equal(Number(after.amount - before.amount), available.toNumber());
+    });
+
+    it("Picks fee tiers from each mint's schedule and processed volume", async () => {
+      const program = anchor.workspace.PaymentSettlement as Program;
+
+      const setSchedule = (scheduleMint: PublicKey | null, tiers: object[]) =>
+        program.methods
+          .setFeeSchedule(scheduleMint, tiers)
+          .accounts({
+            pool: poolAccount,
+            feeSchedule: feeScheduleFor(scheduleMint),
+            authority: payer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+      await setSchedule(null, [
+        { minVolume: new anchor.BN(0), feeBps: 100 },
+        { minVolume: new anchor.BN(5_000_000), feeBps: 50 },
+      ]);
+      await setSchedule(mint, [{ minVolume: new anchor.BN(0), feeBps: 200 }]);
+
+      const recipient = Keypair.generate().publicKey;
+      const amount = new anchor.BN(10_000_000);
+
+      const first = await createSettlement(recipient, amount);
+      let data = await program.account.settlement.fetch(first);
+      assert.equal(data.feeSource.tier[0], 0);
+      assert.equal(data.feeBps, 100);
+
+      await program.methods
+        .processSettlement()
+        .accounts({
+          settlement: first,
+          pool: poolAccount,
+          payer: payer.publicKey,
+          recipient,
+          payerToken: null,
+          poolVault: null,
+          recipientToken: null,
+          mintFees: null,
+          merchantVolume: merchantVolumeFor(recipient),
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      // Processed SOL volume reaches the second SOL tier
+      const second = await createSettlement(recipient, amount);
+      data = await program.account.settlement.fetch(second);
+      assert.equal(data.feeSource.tier[0], 1);
+      assert.equal(data.feeBps, 50);
+
+      // but token settlements use the mint's own schedule and volume
+      const token = await createSettlement(recipient, new anchor.BN(1_000_000), mint);
+      data = await program.account.settlement.fetch(token);
+      assert.equal(data.feeSource.tier[0], 0);
+      assert.equal(data.feeBps, 200);
+
+      // A merchant override wins over the schedule until it is removed
+      const [feeOverride] = PublicKey.findProgramAddressSync(
+        [Buffer.from("fee_override"), recipient.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .setMerchantFeeOverride(recipient, 25)
+        .accounts({
+          pool: poolAccount,
+          feeOverride,
+          authority: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const overridden = await createSettlement(recipient, amount);
+      data = await program.account.settlement.fetch(overridden);
+      assert.ok(data.feeSource.merchantOverride);
+      assert.equal(data.feeBps, 25);
+
+      await program.methods
+        .removeMerchantFeeOverride()
+        .accounts({ pool: poolAccount, feeOverride, authority: payer.publicKey })
+        .rpc();
+      assert.isNull(await provider.connection.getAccountInfo(feeOverride));
+
+      const fallback = await createSettlement(recipient, amount);
+      data = await program.account.settlement.fetch(fallback);
+      assert.equal(data.feeSource.tier[0], 1);
+      assert.equal(data.feeBps, 50);
+
+      await setSchedule(null, []);
+      await setSchedule(mint, []);
+    });
+
     it("Gets pool statistics", async () => {
       const program = anchor.workspace.PaymentSettlement as Program;
 
@@ -1,5 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::system_instruction;
+use anchor_lang::solana_program::{keccak, system_instruction, sysvar};
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+
+use crate::payment_verification::{load_ed25519_signer, Payment, PaymentStatus};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -7,14 +10,28 @@ declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 pub mod payment_escrow {
     use super::*;
 
-    /// Initialize a new payment escrow
-    /// This creates an escrow account that holds SOL until payment is verified
+    /// Initialize a new payment escrow.
+    /// `escrow_id` is chosen by the payer so several escrows can be open at once;
+    /// see `InitializeEscrowArgs` for the escrow's terms.
     pub fn initialize_escrow(
         ctx: Context<InitializeEscrow>,
-        amount: u64,
-        recipient: Pubkey,
-        expiry_timestamp: i64,
+        escrow_id: u64,
+        args: InitializeEscrowArgs,
     ) -> Result<()> {
+        let InitializeEscrowArgs {
+            amount,
+            recipient,
+            expiry_timestamp,
+            mint,
+            proof_hash,
+            milestones,
+            arbiter,
+            facilitator,
+            keeper_tip,
+            recipients,
+            require_verified_payment,
+        } = args;
+
         let escrow = &mut ctx.accounts.escrow;
         let clock = Clock::get()?;
 
@@ -25,59 +42,332 @@ pub mod payment_escrow {
 
         require!(amount > 0, EscrowError::InvalidAmount);
 
+        require!(milestones.len() <= 10, EscrowError::TooManyMilestones);
+
+        if !milestones.is_empty() {
+            let mut total: u64 = 0;
+            let mut previous_deadline = clock.unix_timestamp;
+            for milestone in &milestones {
+                require!(milestone.amount > 0, EscrowError::InvalidAmount);
+                // Deadlines must be ordered and fall before the escrow expiry
+                if let Some(deadline) = milestone.deadline {
+                    require!(
+                        deadline > previous_deadline && deadline <= expiry_timestamp,
+                        EscrowError::InvalidExpiryTimestamp
+                    );
+                    previous_deadline = deadline;
+                }
+                total = total
+                    .checked_add(milestone.amount)
+                    .ok_or(EscrowError::InvalidAmount)?;
+            }
+            require!(total == amount, EscrowError::MilestoneAmountMismatch);
+        }
+
+        // Verification gating matches the Payment record by its proof hash
+        require!(
+            !require_verified_payment || proof_hash.is_some(),
+            EscrowError::MissingProofHash
+        );
+
+        require!(recipients.len() <= 5, EscrowError::TooManyRecipients);
+
+        if !recipients.is_empty() {
+            let mut total_bps: u32 = 0;
+            for (i, share) in recipients.iter().enumerate() {
+                require!(share.share_bps > 0, EscrowError::InvalidShare);
+                require!(
+                    !recipients[..i].iter().any(|other| other.recipient == share.recipient),
+                    EscrowError::DuplicateRecipient
+                );
+                total_bps += share.share_bps as u32;
+            }
+            require!(total_bps == 10000, EscrowError::InvalidShare);
+            require!(
+                recipients.iter().any(|share| share.recipient == recipient),
+                EscrowError::InvalidDustRecipient
+            );
+        }
+
         escrow.payer = ctx.accounts.payer.key();
+        escrow.escrow_id = escrow_id;
         escrow.recipient = recipient;
         escrow.amount = amount;
-        escrow.status = EscrowStatus::Pending;
+        escrow.released_amount = 0;
+        escrow.mint = mint;
+        escrow.funded_amount = 0;
+        escrow.status = EscrowStatus::Created;
         escrow.created_at = clock.unix_timestamp;
         escrow.expiry_timestamp = expiry_timestamp;
         escrow.payment_proof = None;
+        escrow.proof_hash = proof_hash;
+        escrow.milestones = milestones
+            .into_iter()
+            .map(|milestone| Milestone {
+                released: false,
+                ..milestone
+            })
+            .collect();
+        escrow.arbiter = arbiter;
+        escrow.disputed_at = None;
+        escrow.facilitator = facilitator;
+        escrow.keeper_tip = keeper_tip;
+        escrow.version = 0;
+        escrow.recipients = recipients;
+        escrow.require_verified_payment = require_verified_payment;
         escrow.bump = *ctx.bumps.get("escrow").unwrap();
 
-        msg!("Escrow initialized: {} SOL for {}", amount, recipient);
+        match mint {
+            Some(mint) => msg!(
+                "Escrow #{} initialized: {} tokens of {} for {}",
+                escrow_id,
+                amount,
+                mint,
+                recipient
+            ),
+            None => msg!("Escrow #{} initialized: {} SOL for {}", escrow_id, amount, recipient),
+        }
+
+        if proof_hash.is_some() {
+            msg!("Escrow is hashlocked to its payment proof");
+        }
+
+        if require_verified_payment {
+            msg!("  Release requires an on-chain verified payment");
+        }
+
+        if !escrow.milestones.is_empty() {
+            msg!("  Milestones: {}", escrow.milestones.len());
+        }
+
+        if !escrow.recipients.is_empty() {
+            msg!("  Split between {} recipients", escrow.recipients.len());
+        }
+
+        if let Some(arbiter) = arbiter {
+            msg!("  Arbiter: {}", arbiter);
+        }
+
+        if let Some(facilitator) = facilitator {
+            msg!("  Facilitator: {}", facilitator);
+        }
+
+        // Deposit the keeper tip next to the rent so an expiry crank can be paid
+        if keeper_tip > 0 {
+            let transfer_instruction = system_instruction::transfer(
+                &ctx.accounts.payer.key(),
+                &ctx.accounts.escrow.key(),
+                keeper_tip,
+            );
+
+            anchor_lang::solana_program::program::invoke(
+                &transfer_instruction,
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    ctx.accounts.escrow.to_account_info(),
+                ],
+            )?;
+
+            msg!("  Keeper tip: {} lamports", keeper_tip);
+        }
+
+        Ok(())
+    }
+
+    /// Create the PDA-owned token vault for a token-denominated escrow
+    pub fn initialize_escrow_vault(ctx: Context<InitializeEscrowVault>) -> Result<()> {
+        require!(
+            ctx.accounts.escrow.status == EscrowStatus::Created,
+            EscrowError::InvalidEscrowStatus
+        );
+
+        msg!("Escrow vault created for mint {}", ctx.accounts.mint.key());
 
         Ok(())
     }
 
-    /// Fund the escrow account with SOL
-    pub fn fund_escrow(ctx: Context<FundEscrow>) -> Result<()> {
+    /// Fund the escrow account with SOL, or its vault with tokens.
+    /// Partial top-ups accumulate until the escrow amount is reached, at which
+    /// point the escrow moves from `Created` to `Funded`.
+    pub fn fund_escrow(ctx: Context<FundEscrow>, amount: u64) -> Result<()> {
         let escrow = &ctx.accounts.escrow;
 
         require!(
-            escrow.status == EscrowStatus::Pending,
+            escrow.status == EscrowStatus::Created,
+            EscrowError::InvalidEscrowStatus
+        );
+
+        require!(amount > 0, EscrowError::InvalidAmount);
+
+        require!(
+            amount <= escrow.amount - escrow.funded_amount,
+            EscrowError::ExceedsEscrowAmount
+        );
+
+        if escrow.mint.is_some() {
+            let payer_token = ctx
+                .accounts
+                .payer_token
+                .as_ref()
+                .ok_or(EscrowError::MissingTokenAccounts)?;
+            let vault = ctx.accounts.vault.as_ref().ok_or(EscrowError::MissingTokenAccounts)?;
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(EscrowError::MissingTokenAccounts)?;
+
+            // Transfer tokens from payer to the escrow vault
+            token::transfer(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    Transfer {
+                        from: payer_token.to_account_info(),
+                        to: vault.to_account_info(),
+                        authority: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        } else {
+            // Transfer SOL from payer to escrow PDA
+            let transfer_instruction = system_instruction::transfer(
+                &ctx.accounts.payer.key(),
+                &ctx.accounts.escrow.key(),
+                amount,
+            );
+
+            anchor_lang::solana_program::program::invoke(
+                &transfer_instruction,
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    ctx.accounts.escrow.to_account_info(),
+                ],
+            )?;
+        }
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.funded_amount += amount;
+
+        msg!("Escrow funded with {} ({}/{})", amount, escrow.funded_amount, escrow.amount);
+
+        if escrow.funded_amount == escrow.amount {
+            escrow.status = EscrowStatus::Funded;
+            msg!("Escrow fully funded");
+        }
+
+        Ok(())
+    }
+
+    /// Release funds to recipient after payment verification.
+    /// The payer or recipient may release directly; anyone else must be preceded in the
+    /// transaction by an Ed25519 attestation from the escrow's facilitator.
+    /// Hashlocked escrows only release when `payment_proof` hashes to the committed value.
+    /// Split escrows take one destination per share in remaining accounts.
+    /// Milestone escrows release only the next outstanding tranche whose deadline has not passed.
+    pub fn release_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseEscrow<'info>>,
+        payment_proof: String,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(
+            escrow.status != EscrowStatus::Created,
+            EscrowError::EscrowNotFunded
+        );
+
+        require!(
+            escrow.status == EscrowStatus::Funded
+                || escrow.status == EscrowStatus::PartiallyReleased,
             EscrowError::InvalidEscrowStatus
         );
 
-        // Transfer SOL from payer to escrow PDA
-        let transfer_instruction = system_instruction::transfer(
-            &ctx.accounts.payer.key(),
-            &ctx.accounts.escrow.key(),
-            escrow.amount,
+        require!(
+            clock.unix_timestamp < escrow.expiry_timestamp,
+            EscrowError::EscrowExpired
         );
 
-        anchor_lang::solana_program::program::invoke(
-            &transfer_instruction,
-            &[
-                ctx.accounts.payer.to_account_info(),
-                ctx.accounts.escrow.to_account_info(),
-            ],
+        check_verified_payment(escrow, ctx.accounts.verified_payment.as_ref())?;
+
+        let (amount, milestone_index) = if escrow.milestones.is_empty() {
+            check_payment_proof(escrow.proof_hash, &payment_proof)?;
+            (escrow.amount - escrow.released_amount, None)
+        } else {
+            let escrow_proof_hash = escrow.proof_hash;
+            let (index, milestone) = escrow
+                .milestones
+                .iter_mut()
+                .enumerate()
+                .find(|(_, milestone)| {
+                    !milestone.released
+                        && milestone
+                            .deadline
+                            .map_or(true, |deadline| clock.unix_timestamp < deadline)
+                })
+                .ok_or(EscrowError::NothingToRelease)?;
+
+            check_payment_proof(milestone.proof_hash.or(escrow_proof_hash), &payment_proof)?;
+
+            milestone.released = true;
+            (milestone.amount, Some(index as u8))
+        };
+
+        require!(amount > 0, EscrowError::NothingToRelease);
+
+        authorize_release(
+            escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.instructions.as_ref(),
+            amount,
+            milestone_index,
+        )?;
+
+        // Transfer funds from escrow to recipient
+        let destination = destination_account(
+            &ctx.accounts.recipient,
+            &ctx.accounts.recipient_token,
+        );
+        pay_recipients(
+            escrow,
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            &destination,
+            ctx.remaining_accounts,
+            amount,
         )?;
 
-        msg!("Escrow funded with {} lamports", escrow.amount);
+        escrow.released_amount += amount;
+        escrow.status = if escrow.released_amount == escrow.amount {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::PartiallyReleased
+        };
+        escrow.payment_proof = Some(payment_proof.clone());
+
+        msg!("Escrow released: {} to {}", amount, escrow.recipient);
+        msg!("Payment proof: {}", payment_proof);
 
         Ok(())
     }
 
-    /// Release funds to recipient after payment verification
-    pub fn release_escrow(
-        ctx: Context<ReleaseEscrow>,
+    /// Release a single milestone tranche to the recipient
+    pub fn release_milestone<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseEscrow<'info>>,
+        milestone_index: u8,
         payment_proof: String,
     ) -> Result<()> {
         let escrow = &mut ctx.accounts.escrow;
         let clock = Clock::get()?;
 
         require!(
-            escrow.status == EscrowStatus::Pending,
+            escrow.status != EscrowStatus::Created,
+            EscrowError::EscrowNotFunded
+        );
+
+        require!(
+            escrow.status == EscrowStatus::Funded
+                || escrow.status == EscrowStatus::PartiallyReleased,
             EscrowError::InvalidEscrowStatus
         );
 
@@ -86,32 +376,73 @@ pub mod payment_escrow {
             EscrowError::EscrowExpired
         );
 
+        check_verified_payment(escrow, ctx.accounts.verified_payment.as_ref())?;
+
         require!(
-            ctx.accounts.authority.key() == escrow.payer
-                || ctx.accounts.authority.key() == escrow.recipient,
-            EscrowError::Unauthorized
+            (milestone_index as usize) < escrow.milestones.len(),
+            EscrowError::InvalidMilestoneIndex
         );
 
-        // Transfer SOL from escrow to recipient
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= escrow.amount;
-        **ctx.accounts.recipient.try_borrow_mut_lamports()? += escrow.amount;
+        let escrow_proof_hash = escrow.proof_hash;
+        let milestone = &mut escrow.milestones[milestone_index as usize];
+
+        require!(!milestone.released, EscrowError::MilestoneAlreadyReleased);
+
+        check_payment_proof(milestone.proof_hash.or(escrow_proof_hash), &payment_proof)?;
+
+        if let Some(deadline) = milestone.deadline {
+            require!(clock.unix_timestamp < deadline, EscrowError::MilestoneExpired);
+        }
+
+        milestone.released = true;
+        let amount = milestone.amount;
+
+        authorize_release(
+            escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.instructions.as_ref(),
+            amount,
+            Some(milestone_index),
+        )?;
+
+        // Transfer the tranche from escrow to recipient
+        let destination = destination_account(
+            &ctx.accounts.recipient,
+            &ctx.accounts.recipient_token,
+        );
+        pay_recipients(
+            escrow,
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            &destination,
+            ctx.remaining_accounts,
+            amount,
+        )?;
 
-        escrow.status = EscrowStatus::Released;
+        escrow.released_amount += amount;
+        escrow.status = if escrow.released_amount == escrow.amount {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::PartiallyReleased
+        };
         escrow.payment_proof = Some(payment_proof.clone());
 
-        msg!("Escrow released: {} lamports to {}", escrow.amount, escrow.recipient);
-        msg!("Payment proof: {}", payment_proof);
+        msg!("Milestone #{} released: {} to {}", milestone_index, amount, escrow.recipient);
+        msg!("  Released so far: {}/{}", escrow.released_amount, escrow.amount);
 
         Ok(())
     }
 
-    /// Refund escrow to payer if expired or cancelled
+    /// Refund escrow to payer if expired or cancelled.
+    /// Only funds actually deposited and not yet released are returned.
     pub fn refund_escrow(ctx: Context<RefundEscrow>) -> Result<()> {
         let escrow = &mut ctx.accounts.escrow;
         let clock = Clock::get()?;
 
         require!(
-            escrow.status == EscrowStatus::Pending,
+            escrow.status == EscrowStatus::Created
+                || escrow.status == EscrowStatus::Funded
+                || escrow.status == EscrowStatus::PartiallyReleased,
             EscrowError::InvalidEscrowStatus
         );
 
@@ -121,13 +452,379 @@ pub mod payment_escrow {
 
         require!(can_refund, EscrowError::CannotRefund);
 
-        // Transfer SOL back to payer
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= escrow.amount;
-        **ctx.accounts.payer.try_borrow_mut_lamports()? += escrow.amount;
+        // With an arbiter, funded escrows cannot be pulled back before expiry;
+        // the payer has to raise a dispute instead
+        require!(
+            escrow.arbiter.is_none()
+                || escrow.status == EscrowStatus::Created
+                || clock.unix_timestamp >= escrow.expiry_timestamp,
+            EscrowError::CannotRefund
+        );
+
+        let amount = escrow.funded_amount - escrow.released_amount;
+
+        // Transfer funds back to payer
+        if amount > 0 {
+            let destination = destination_account(
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.payer_token,
+            );
+            pay_from_escrow(
+                escrow,
+                ctx.accounts.vault.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &destination,
+                escrow.payer,
+                amount,
+            )?;
+        }
+
+        escrow.status = EscrowStatus::Refunded;
+
+        msg!("Escrow refunded: {} to payer", amount);
+
+        Ok(())
+    }
+
+    /// Amend an open escrow (payer and recipient co-sign). Can extend the expiry,
+    /// reduce the amount (refunding any excess deposit to the payer) or change the
+    /// recipient. `expected_version` must match so stale approvals are rejected.
+    pub fn amend_escrow(
+        ctx: Context<AmendEscrow>,
+        expected_version: u32,
+        new_expiry: Option<i64>,
+        new_amount: Option<u64>,
+        new_recipient: Option<Pubkey>,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(
+            escrow.status == EscrowStatus::Created
+                || escrow.status == EscrowStatus::Funded
+                || escrow.status == EscrowStatus::PartiallyReleased,
+            EscrowError::InvalidEscrowStatus
+        );
+
+        require!(
+            ctx.accounts.payer.key() == escrow.payer
+                && ctx.accounts.recipient.key() == escrow.recipient,
+            EscrowError::Unauthorized
+        );
+
+        require!(expected_version == escrow.version, EscrowError::StaleAmendment);
+
+        if let Some(new_expiry) = new_expiry {
+            require!(
+                new_expiry > clock.unix_timestamp,
+                EscrowError::InvalidExpiryTimestamp
+            );
+
+            for milestone in &escrow.milestones {
+                if let Some(deadline) = milestone.deadline {
+                    require!(deadline <= new_expiry, EscrowError::InvalidExpiryTimestamp);
+                }
+            }
+
+            msg!("Expiry amended: {} -> {}", escrow.expiry_timestamp, new_expiry);
+            escrow.expiry_timestamp = new_expiry;
+        }
+
+        if let Some(new_amount) = new_amount {
+            require!(escrow.milestones.is_empty(), EscrowError::MilestonesLocked);
+            require!(
+                new_amount > 0 && new_amount < escrow.amount,
+                EscrowError::InvalidAmount
+            );
+
+            // Anything deposited above the new amount goes back to the payer
+            let refund = escrow.funded_amount.saturating_sub(new_amount);
+            if refund > 0 {
+                let destination = destination_account(
+                    &ctx.accounts.payer.to_account_info(),
+                    &ctx.accounts.payer_token,
+                );
+                pay_from_escrow(
+                    escrow,
+                    ctx.accounts.vault.as_ref(),
+                    ctx.accounts.token_program.as_ref(),
+                    &destination,
+                    escrow.payer,
+                    refund,
+                )?;
+                escrow.funded_amount -= refund;
+            }
+
+            msg!("Amount amended: {} -> {} (refunded {})", escrow.amount, new_amount, refund);
+            escrow.amount = new_amount;
+
+            if escrow.status == EscrowStatus::Created && escrow.funded_amount == escrow.amount {
+                escrow.status = EscrowStatus::Funded;
+            }
+        }
+
+        if let Some(new_recipient) = new_recipient {
+            require!(escrow.recipients.is_empty(), EscrowError::RecipientsLocked);
+            msg!("Recipient amended: {} -> {}", escrow.recipient, new_recipient);
+            escrow.recipient = new_recipient;
+        }
+
+        escrow.version += 1;
+
+        msg!("Escrow amended to version {}", escrow.version);
+
+        Ok(())
+    }
+
+    /// Raise a dispute (payer or recipient), freezing release and refund until the arbiter
+    /// rules or `Escrow::DISPUTE_TIMEOUT_SECONDS` pass (see `expire_escrow_dispute`)
+    pub fn raise_escrow_dispute(
+        ctx: Context<RaiseEscrowDispute>,
+        reason: String,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(escrow.arbiter.is_some(), EscrowError::NoArbiter);
+
+        require!(
+            escrow.status == EscrowStatus::Funded
+                || escrow.status == EscrowStatus::PartiallyReleased,
+            EscrowError::InvalidEscrowStatus
+        );
+
+        require!(
+            ctx.accounts.disputer.key() == escrow.payer
+                || ctx.accounts.disputer.key() == escrow.recipient,
+            EscrowError::Unauthorized
+        );
+
+        escrow.status = EscrowStatus::Disputed;
+        escrow.disputed_at = Some(clock.unix_timestamp);
+
+        msg!("Escrow disputed by {}", ctx.accounts.disputer.key());
+        msg!("  Reason: {}", reason);
+
+        Ok(())
+    }
+
+    /// Resolve a dispute (arbiter only), splitting the held funds between the parties.
+    /// `recipient_share_bps` is the recipient's share in basis points; the payer gets the rest.
+    pub fn arbiter_resolve<'info>(
+        ctx: Context<'_, '_, '_, 'info, ArbiterResolve<'info>>,
+        recipient_share_bps: u16,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(
+            escrow.status == EscrowStatus::Disputed,
+            EscrowError::InvalidEscrowStatus
+        );
+
+        require!(
+            Some(ctx.accounts.arbiter.key()) == escrow.arbiter,
+            EscrowError::Unauthorized
+        );
+
+        require!(recipient_share_bps <= 10000, EscrowError::InvalidShare);
+
+        let held = escrow.funded_amount - escrow.released_amount;
+        let to_recipient = (held as u128 * recipient_share_bps as u128 / 10000) as u64;
+        let to_payer = held - to_recipient;
+
+        if to_recipient > 0 {
+            let destination = destination_account(
+                &ctx.accounts.recipient,
+                &ctx.accounts.recipient_token,
+            );
+            pay_recipients(
+                escrow,
+                ctx.accounts.vault.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &destination,
+                ctx.remaining_accounts,
+                to_recipient,
+            )?;
+        }
+
+        if to_payer > 0 {
+            let destination = destination_account(
+                &ctx.accounts.payer,
+                &ctx.accounts.payer_token,
+            );
+            pay_from_escrow(
+                escrow,
+                ctx.accounts.vault.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &destination,
+                escrow.payer,
+                to_payer,
+            )?;
+        }
+
+        escrow.released_amount += to_recipient;
+        escrow.status = EscrowStatus::Resolved;
+
+        msg!("Escrow dispute resolved by arbiter:");
+        msg!("  To recipient: {}", to_recipient);
+        msg!("  To payer: {}", to_payer);
+
+        Ok(())
+    }
+
+    /// Refund a dispute the arbiter has not resolved within `Escrow::DISPUTE_TIMEOUT_SECONDS`
+    /// of it being raised (permissionless). Held funds go back to the payer.
+    pub fn expire_escrow_dispute(ctx: Context<ExpireEscrowDispute>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(
+            escrow.status == EscrowStatus::Disputed,
+            EscrowError::InvalidEscrowStatus
+        );
+
+        let disputed_at = escrow.disputed_at.ok_or(EscrowError::InvalidEscrowStatus)?;
+        require!(
+            clock.unix_timestamp >= disputed_at + Escrow::DISPUTE_TIMEOUT_SECONDS,
+            EscrowError::DisputeTimeoutNotReached
+        );
+
+        let held = escrow.funded_amount - escrow.released_amount;
+
+        if held > 0 {
+            let destination = destination_account(
+                &ctx.accounts.payer,
+                &ctx.accounts.payer_token,
+            );
+            pay_from_escrow(
+                escrow,
+                ctx.accounts.vault.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &destination,
+                escrow.payer,
+                held,
+            )?;
+        }
 
         escrow.status = EscrowStatus::Refunded;
 
-        msg!("Escrow refunded: {} lamports to payer", escrow.amount);
+        msg!("Escrow dispute timed out without a ruling");
+        msg!("  Refunded {} to payer", held);
+
+        Ok(())
+    }
+
+    /// Sweep expired escrows (permissionless). Each escrow is passed in remaining
+    /// accounts as `[escrow, payer]`, followed by `[vault, payer_token]` for token
+    /// escrows. Unreleased funds go back to the payer, the account is closed to the
+    /// payer and the keeper collects each escrow's tip. Escrows that were refunded
+    /// but never closed are closed the same way.
+    pub fn crank_expired<'info>(ctx: Context<'_, '_, '_, 'info, CrankExpired<'info>>) -> Result<()> {
+        let clock = Clock::get()?;
+        let keeper = ctx.accounts.keeper.to_account_info();
+        let token_program = ctx.accounts.token_program.as_ref();
+
+        let mut accounts = ctx.remaining_accounts.iter();
+        let mut swept: u32 = 0;
+        let mut total_tips: u64 = 0;
+
+        while let Some(escrow_info) = accounts.next() {
+            let escrow = Account::<Escrow>::try_from(escrow_info)?;
+            let payer_info = accounts.next().ok_or(EscrowError::MissingCrankAccounts)?;
+
+            let escrow_id = escrow.escrow_id.to_le_bytes();
+            let expected = Pubkey::create_program_address(
+                &[b"escrow", escrow.payer.as_ref(), &escrow_id, &[escrow.bump]],
+                ctx.program_id,
+            )
+            .map_err(|_| EscrowError::InvalidEscrowAccount)?;
+            require!(escrow_info.key() == expected, EscrowError::InvalidEscrowAccount);
+
+            require!(payer_info.key() == escrow.payer, EscrowError::InvalidDestination);
+
+            require!(
+                clock.unix_timestamp >= escrow.expiry_timestamp,
+                EscrowError::EscrowNotExpired
+            );
+
+            require!(
+                escrow.status == EscrowStatus::Created
+                    || escrow.status == EscrowStatus::Funded
+                    || escrow.status == EscrowStatus::PartiallyReleased
+                    || escrow.status == EscrowStatus::Refunded,
+                EscrowError::InvalidEscrowStatus
+            );
+
+            // Refunded escrows have already returned their funds
+            let held = if escrow.status == EscrowStatus::Refunded {
+                0
+            } else {
+                escrow.funded_amount - escrow.released_amount
+            };
+
+            if escrow.mint.is_some() {
+                let vault_info = accounts.next().ok_or(EscrowError::MissingCrankAccounts)?;
+                let payer_token_info = accounts.next().ok_or(EscrowError::MissingCrankAccounts)?;
+                let token_program = token_program.ok_or(EscrowError::MissingTokenAccounts)?;
+
+                let (expected_vault, _) = Pubkey::find_program_address(
+                    &[b"vault", escrow_info.key.as_ref()],
+                    ctx.program_id,
+                );
+                require!(
+                    vault_info.key() == expected_vault,
+                    EscrowError::InvalidEscrowAccount
+                );
+
+                // A vault that was never created cannot hold funds and has nothing to close
+                if vault_info.data_is_empty() {
+                    require!(held == 0, EscrowError::InvalidEscrowAccount);
+                } else {
+                    let vault = Account::<TokenAccount>::try_from(vault_info)?;
+                    require!(vault.owner == escrow_info.key(), EscrowError::InvalidEscrowAccount);
+
+                    if held > 0 {
+                        pay_from_escrow(
+                            &escrow,
+                            Some(&vault),
+                            Some(token_program),
+                            payer_token_info,
+                            escrow.payer,
+                            held,
+                        )?;
+                    }
+
+                    let bump = [escrow.bump];
+                    let seeds: &[&[u8]] = &[b"escrow", escrow.payer.as_ref(), &escrow_id, &bump];
+
+                    token::close_account(CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        CloseAccount {
+                            account: vault_info.clone(),
+                            destination: payer_info.clone(),
+                            authority: escrow_info.clone(),
+                        },
+                        &[seeds],
+                    ))?;
+                }
+            } else if held > 0 {
+                pay_from_escrow(&escrow, None, None, payer_info, escrow.payer, held)?;
+            }
+
+            // Pay the keeper, then close the escrow to the payer
+            **escrow_info.try_borrow_mut_lamports()? -= escrow.keeper_tip;
+            **keeper.try_borrow_mut_lamports()? += escrow.keeper_tip;
+            total_tips += escrow.keeper_tip;
+
+            escrow.close(payer_info.clone())?;
+
+            swept += 1;
+        }
+
+        require!(swept > 0, EscrowError::MissingCrankAccounts);
+
+        msg!("Swept {} expired escrows", swept);
+        msg!("  Keeper tips: {} lamports", total_tips);
 
         Ok(())
     }
@@ -137,23 +834,288 @@ pub mod payment_escrow {
         let escrow = &ctx.accounts.escrow;
 
         require!(
-            escrow.status == EscrowStatus::Released || escrow.status == EscrowStatus::Refunded,
+            escrow.status == EscrowStatus::Released
+                || escrow.status == EscrowStatus::Refunded
+                || escrow.status == EscrowStatus::Resolved,
             EscrowError::CannotCloseEscrow
         );
 
+        // Token escrows also close their (now empty) vault. A vault that was never
+        // created (the escrow was refunded before funding) has nothing to close.
+        if escrow.mint.is_some() {
+            let vault = ctx.accounts.vault.as_ref().ok_or(EscrowError::MissingTokenAccounts)?;
+
+            if !vault.data_is_empty() {
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(EscrowError::MissingTokenAccounts)?;
+
+                let escrow_id = escrow.escrow_id.to_le_bytes();
+                let bump = [escrow.bump];
+                let seeds: &[&[u8]] = &[b"escrow", escrow.payer.as_ref(), &escrow_id, &bump];
+
+                token::close_account(CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    CloseAccount {
+                        account: vault.to_account_info(),
+                        destination: ctx.accounts.payer.to_account_info(),
+                        authority: escrow.to_account_info(),
+                    },
+                    &[seeds],
+                ))?;
+            }
+        }
+
         msg!("Escrow account closed");
 
         Ok(())
     }
 }
 
+/// Enforce the proof length and, when hashlocked, that the proof matches the commitment
+fn check_payment_proof(proof_hash: Option<[u8; 32]>, payment_proof: &str) -> Result<()> {
+    require!(payment_proof.len() <= 256, EscrowError::PaymentProofTooLong);
+
+    if let Some(proof_hash) = proof_hash {
+        require!(
+            keccak::hash(payment_proof.as_bytes()).to_bytes() == proof_hash,
+            EscrowError::InvalidPaymentProof
+        );
+    }
+
+    Ok(())
+}
+
+/// For escrows gated on verification, require the payment_verification record whose
+/// `payment_hash` matches the escrow's committed proof and that is Verified or Settled.
+/// The record must also describe this escrow's payment: same payer and recipient, at
+/// least the escrow amount, and in the escrow's asset.
+fn check_verified_payment(escrow: &Escrow, verified_payment: Option<&Account<Payment>>) -> Result<()> {
+    if !escrow.require_verified_payment {
+        return Ok(());
+    }
+
+    let payment = verified_payment.ok_or(EscrowError::PaymentNotVerified)?;
+
+    require!(
+        Some(payment.payment_hash) == escrow.proof_hash,
+        EscrowError::PaymentMismatch
+    );
+
+    require!(
+        payment.payer == escrow.payer && payment.recipient == escrow.recipient,
+        EscrowError::PaymentMismatch
+    );
+
+    require!(payment.amount >= escrow.amount, EscrowError::PaymentMismatch);
+
+    // SOL escrows expect the native asset, recorded as the default pubkey
+    require!(
+        payment.asset == escrow.mint.unwrap_or_default(),
+        EscrowError::PaymentMismatch
+    );
+
+    require!(
+        payment.status == PaymentStatus::Verified || payment.status == PaymentStatus::Settled,
+        EscrowError::PaymentNotVerified
+    );
+
+    Ok(())
+}
+
+/// Payer and recipient may always release. Anyone else needs the escrow's facilitator
+/// to have signed `escrow || recipient || amount || released_amount || milestone` with
+/// Ed25519, checked through the Ed25519 program instruction placed right before this one.
+/// `milestone` is the released tranche's index, or `u8::MAX` for escrows without milestones,
+/// so an attestation for one tranche cannot release another of the same amount.
+fn authorize_release(
+    escrow: &Account<Escrow>,
+    authority: &Pubkey,
+    instructions: Option<&UncheckedAccount>,
+    amount: u64,
+    milestone_index: Option<u8>,
+) -> Result<()> {
+    if *authority == escrow.payer || *authority == escrow.recipient {
+        return Ok(());
+    }
+
+    let facilitator = escrow.facilitator.ok_or(EscrowError::Unauthorized)?;
+    let instructions = instructions.ok_or(EscrowError::MissingAttestation)?;
+
+    let mut message = Vec::with_capacity(81);
+    message.extend_from_slice(escrow.key().as_ref());
+    message.extend_from_slice(escrow.recipient.as_ref());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&escrow.released_amount.to_le_bytes());
+    message.push(milestone_index.unwrap_or(u8::MAX));
+
+    let signer = load_ed25519_signer(&instructions.to_account_info(), &message)?;
+    require!(signer == facilitator, EscrowError::InvalidAttestation);
+
+    Ok(())
+}
+
+/// Pay `amount` to the escrow's recipient side. Single-recipient escrows pay
+/// `destination`; split escrows pay each share to the matching entry of
+/// `remaining_accounts` (in share order) and give the rounding dust to `recipient`.
+fn pay_recipients<'info>(
+    escrow: &Account<'info, Escrow>,
+    vault: Option<&Account<'info, TokenAccount>>,
+    token_program: Option<&Program<'info, Token>>,
+    destination: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<()> {
+    if escrow.recipients.is_empty() {
+        return pay_from_escrow(
+            escrow,
+            vault,
+            token_program,
+            destination,
+            escrow.recipient,
+            amount,
+        );
+    }
+
+    require!(
+        remaining_accounts.len() >= escrow.recipients.len(),
+        EscrowError::MissingRecipientAccounts
+    );
+
+    let mut portions: Vec<u64> = escrow
+        .recipients
+        .iter()
+        .map(|share| (amount as u128 * share.share_bps as u128 / 10000) as u64)
+        .collect();
+
+    let dust = amount - portions.iter().sum::<u64>();
+    let dust_index = escrow
+        .recipients
+        .iter()
+        .position(|share| share.recipient == escrow.recipient)
+        .ok_or(EscrowError::InvalidDustRecipient)?;
+    portions[dust_index] += dust;
+
+    for ((share, portion), destination) in escrow
+        .recipients
+        .iter()
+        .zip(portions)
+        .zip(remaining_accounts)
+    {
+        if portion > 0 {
+            pay_from_escrow(
+                escrow,
+                vault,
+                token_program,
+                destination,
+                share.recipient,
+                portion,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Pick the token account when one was supplied, otherwise the wallet itself
+fn destination_account<'info>(
+    wallet: &AccountInfo<'info>,
+    token_account: &Option<Account<'info, TokenAccount>>,
+) -> AccountInfo<'info> {
+    match token_account {
+        Some(token_account) => token_account.to_account_info(),
+        None => wallet.clone(),
+    }
+}
+
+/// Move `amount` out of the escrow to `destination`, which must belong to `beneficiary`.
+/// SOL escrows pay lamports straight from the escrow PDA; token escrows transfer
+/// from the vault with the escrow PDA signing.
+fn pay_from_escrow<'info>(
+    escrow: &Account<'info, Escrow>,
+    vault: Option<&Account<'info, TokenAccount>>,
+    token_program: Option<&Program<'info, Token>>,
+    destination: &AccountInfo<'info>,
+    beneficiary: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    match escrow.mint {
+        None => {
+            require!(
+                destination.key() == beneficiary,
+                EscrowError::InvalidDestination
+            );
+
+            **escrow.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **destination.try_borrow_mut_lamports()? += amount;
+        }
+        Some(mint) => {
+            let vault = vault.ok_or(EscrowError::MissingTokenAccounts)?;
+            let token_program = token_program.ok_or(EscrowError::MissingTokenAccounts)?;
+
+            let destination_token = Account::<TokenAccount>::try_from(destination)?;
+            require!(
+                destination_token.owner == beneficiary,
+                EscrowError::InvalidDestination
+            );
+            require!(destination_token.mint == mint, EscrowError::InvalidMint);
+
+            let escrow_id = escrow.escrow_id.to_le_bytes();
+            let bump = [escrow.bump];
+            let seeds: &[&[u8]] = &[b"escrow", escrow.payer.as_ref(), &escrow_id, &bump];
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    Transfer {
+                        from: vault.to_account_info(),
+                        to: destination.clone(),
+                        authority: escrow.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                amount,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Terms of a new escrow
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitializeEscrowArgs {
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub expiry_timestamp: i64,
+    /// SPL mint for a token escrow (vault created with `initialize_escrow_vault`), `None` for SOL
+    pub mint: Option<Pubkey>,
+    /// keccak256 of the x402 proof or preimage that must be revealed on release
+    pub proof_hash: Option<[u8; 32]>,
+    /// Ordered tranches adding up to `amount`; each may carry its own hashlock
+    pub milestones: Vec<Milestone>,
+    /// Settles disputes raised by either party
+    pub arbiter: Option<Pubkey>,
+    /// Key allowed to authorize releases with an Ed25519 attestation
+    pub facilitator: Option<Pubkey>,
+    /// Lamports paid to whoever cranks the escrow after expiry
+    pub keeper_tip: u64,
+    /// Basis-point split of every payout; `recipient` must be listed and gets the dust
+    pub recipients: Vec<RecipientShare>,
+    /// Also require a Verified/Settled payment_verification record for `proof_hash`
+    pub require_verified_payment: bool,
+}
+
 #[derive(Accounts)]
+#[instruction(escrow_id: u64)]
 pub struct InitializeEscrow<'info> {
     #[account(
         init,
         payer = payer,
         space = 8 + Escrow::INIT_SPACE,
-        seeds = [b"escrow", payer.key().as_ref()],
+        seeds = [b"escrow", payer.key().as_ref(), &escrow_id.to_le_bytes()],
         bump
     )]
     pub escrow: Account<'info, Escrow>,
@@ -165,13 +1127,63 @@ pub struct InitializeEscrow<'info> {
 }
 
 #[derive(Accounts)]
-pub struct FundEscrow<'info> {
+pub struct InitializeEscrowVault<'info> {
+    #[account(
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+        has_one = payer,
+        constraint = escrow.mint == Some(mint.key()) @ EscrowError::InvalidMint,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint,
+        token::authority = escrow,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
     #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct FundEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+    )]
     pub escrow: Account<'info, Escrow>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    /// Payer's token account (token escrows only)
+    #[account(mut)]
+    pub payer_token: Option<Account<'info, TokenAccount>>,
+
+    /// Escrow vault (token escrows only)
+    #[account(
+        mut,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -179,7 +1191,7 @@ pub struct FundEscrow<'info> {
 pub struct ReleaseEscrow<'info> {
     #[account(
         mut,
-        seeds = [b"escrow", escrow.payer.as_ref()],
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
         bump = escrow.bump,
     )]
     pub escrow: Account<'info, Escrow>,
@@ -188,6 +1200,27 @@ pub struct ReleaseEscrow<'info> {
     #[account(mut)]
     pub recipient: AccountInfo<'info>,
 
+    /// Recipient's token account (token escrows only)
+    #[account(mut)]
+    pub recipient_token: Option<Account<'info, TokenAccount>>,
+
+    /// Escrow vault (token escrows only)
+    #[account(
+        mut,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// Verified payment record (escrows gated on payment_verification only)
+    pub verified_payment: Option<Account<'info, Payment>>,
+
+    /// CHECK: Instructions sysvar (facilitator-attested releases only)
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
     pub authority: Signer<'info>,
 }
 
@@ -195,13 +1228,143 @@ pub struct ReleaseEscrow<'info> {
 pub struct RefundEscrow<'info> {
     #[account(
         mut,
-        seeds = [b"escrow", escrow.payer.as_ref()],
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
         bump = escrow.bump,
     )]
     pub escrow: Account<'info, Escrow>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
+
+    /// Payer's token account (token escrows only)
+    #[account(mut)]
+    pub payer_token: Option<Account<'info, TokenAccount>>,
+
+    /// Escrow vault (token escrows only)
+    #[account(
+        mut,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct AmendEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub recipient: Signer<'info>,
+
+    /// Payer's token account (token escrows only)
+    #[account(mut)]
+    pub payer_token: Option<Account<'info, TokenAccount>>,
+
+    /// Escrow vault (token escrows only)
+    #[account(
+        mut,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct RaiseEscrowDispute<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    pub disputer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ArbiterResolve<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// CHECK: Recipient receiving its share of the funds
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Recipient's token account (token escrows only)
+    #[account(mut)]
+    pub recipient_token: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Payer receiving its share of the funds
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    /// Payer's token account (token escrows only)
+    #[account(mut)]
+    pub payer_token: Option<Account<'info, TokenAccount>>,
+
+    /// Escrow vault (token escrows only)
+    #[account(
+        mut,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub arbiter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireEscrowDispute<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// CHECK: Payer receiving the refund; checked against `escrow.payer` on payout
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    /// Payer's token account (token escrows only)
+    #[account(mut)]
+    pub payer_token: Option<Account<'info, TokenAccount>>,
+
+    /// Escrow vault (token escrows only)
+    #[account(
+        mut,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct CrankExpired<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    /// Required when the batch contains token escrows
+    pub token_program: Option<Program<'info, Token>>,
 }
 
 #[derive(Accounts)]
@@ -209,34 +1372,84 @@ pub struct CloseEscrow<'info> {
     #[account(
         mut,
         close = payer,
-        seeds = [b"escrow", escrow.payer.as_ref()],
+        has_one = payer,
+        seeds = [b"escrow", escrow.payer.as_ref(), &escrow.escrow_id.to_le_bytes()],
         bump = escrow.bump,
     )]
     pub escrow: Account<'info, Escrow>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
+
+    /// CHECK: Escrow vault PDA (token escrows only); may not exist if the escrow
+    /// was never funded, in which case there is nothing to close
+    #[account(
+        mut,
+        seeds = [b"vault", escrow.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Option<Program<'info, Token>>,
 }
 
 #[account]
 #[derive(InitSpace)]
 pub struct Escrow {
     pub payer: Pubkey,          // 32 bytes
+    pub escrow_id: u64,         // 8 bytes
     pub recipient: Pubkey,      // 32 bytes
     pub amount: u64,            // 8 bytes
+    pub funded_amount: u64,     // 8 bytes
+    pub released_amount: u64,   // 8 bytes
+    pub mint: Option<Pubkey>,   // 33 bytes (None for SOL escrows)
     pub status: EscrowStatus,   // 1 byte
     pub created_at: i64,        // 8 bytes
     pub expiry_timestamp: i64,  // 8 bytes
     #[max_len(256)]
     pub payment_proof: Option<String>, // 4 + 256 bytes
+    pub proof_hash: Option<[u8; 32]>,  // 33 bytes (keccak256 hashlock)
+    #[max_len(10)]
+    pub milestones: Vec<Milestone>,    // 4 + (51 * 10) bytes
+    pub arbiter: Option<Pubkey>,       // 33 bytes
+    pub disputed_at: Option<i64>,      // 9 bytes
+    pub facilitator: Option<Pubkey>,   // 33 bytes
+    pub keeper_tip: u64,               // 8 bytes
+    pub version: u32,                  // 4 bytes (bumped on every amendment)
+    #[max_len(5)]
+    pub recipients: Vec<RecipientShare>, // 4 + (34 * 5) bytes
+    pub require_verified_payment: bool, // 1 byte
     pub bump: u8,               // 1 byte
 }
 
+impl Escrow {
+    /// How long the arbiter has to rule before a dispute falls back to a refund
+    pub const DISPUTE_TIMEOUT_SECONDS: i64 = 14 * 24 * 60 * 60;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RecipientShare {
+    pub recipient: Pubkey,      // 32 bytes
+    pub share_bps: u16,         // 2 bytes (basis points)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct Milestone {
+    pub amount: u64,            // 8 bytes
+    pub deadline: Option<i64>,  // 9 bytes
+    pub proof_hash: Option<[u8; 32]>, // 33 bytes (per-tranche keccak256 hashlock)
+    pub released: bool,         // 1 byte
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum EscrowStatus {
-    Pending,
+    Created,
+    Funded,
+    PartiallyReleased,
     Released,
     Refunded,
+    Disputed,
+    Resolved,
 }
 
 #[error_code]
@@ -261,4 +1474,97 @@ pub enum EscrowError {
 
     #[msg("Cannot close escrow")]
     CannotCloseEscrow,
+
+    #[msg("Token accounts are required for token escrows")]
+    MissingTokenAccounts,
+
+    #[msg("Token account mint does not match escrow mint")]
+    InvalidMint,
+
+    #[msg("Destination account does not belong to the beneficiary")]
+    InvalidDestination,
+
+    #[msg("Payment proof too long")]
+    PaymentProofTooLong,
+
+    #[msg("Payment proof does not match the escrow hashlock")]
+    InvalidPaymentProof,
+
+    #[msg("Too many milestones")]
+    TooManyMilestones,
+
+    #[msg("Milestone amounts must add up to the escrow amount")]
+    MilestoneAmountMismatch,
+
+    #[msg("Invalid milestone index")]
+    InvalidMilestoneIndex,
+
+    #[msg("Milestone already released")]
+    MilestoneAlreadyReleased,
+
+    #[msg("Milestone deadline has passed")]
+    MilestoneExpired,
+
+    #[msg("Nothing to release")]
+    NothingToRelease,
+
+    #[msg("Escrow is not fully funded")]
+    EscrowNotFunded,
+
+    #[msg("Deposit exceeds the escrow amount")]
+    ExceedsEscrowAmount,
+
+    #[msg("Escrow has no arbiter")]
+    NoArbiter,
+
+    #[msg("Invalid share in basis points")]
+    InvalidShare,
+
+    #[msg("Missing facilitator attestation")]
+    MissingAttestation,
+
+    #[msg("Invalid facilitator attestation")]
+    InvalidAttestation,
+
+    #[msg("Escrow has not expired")]
+    EscrowNotExpired,
+
+    #[msg("Missing accounts for expiry crank")]
+    MissingCrankAccounts,
+
+    #[msg("Invalid escrow account")]
+    InvalidEscrowAccount,
+
+    #[msg("Amendment was approved for a stale escrow version")]
+    StaleAmendment,
+
+    #[msg("Milestone escrow amounts cannot be amended")]
+    MilestonesLocked,
+
+    #[msg("Too many recipients")]
+    TooManyRecipients,
+
+    #[msg("Duplicate recipient")]
+    DuplicateRecipient,
+
+    #[msg("Dust recipient must be one of the split recipients")]
+    InvalidDustRecipient,
+
+    #[msg("Missing destination accounts for split recipients")]
+    MissingRecipientAccounts,
+
+    #[msg("Split escrow recipients cannot be amended")]
+    RecipientsLocked,
+
+    #[msg("Verification-gated escrows need a proof hash")]
+    MissingProofHash,
+
+    #[msg("Payment has not been verified on-chain")]
+    PaymentNotVerified,
+
+    #[msg("Payment record does not match the escrow proof")]
+    PaymentMismatch,
+
+    #[msg("Dispute timeout has not been reached")]
+    DisputeTimeoutNotReached,
 }
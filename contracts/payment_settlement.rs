@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::system_instruction;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -19,6 +21,8 @@ pub mod payment_settlement {
         pool.authority = ctx.accounts.authority.key();
         pool.total_settled = 0;
         pool.total_fees_collected = 0;
+        pool.total_fees_withdrawn = 0;
+        pool.fee_destination = None;
         pool.fee_percentage = fee_percentage;
         pool.active_settlements = 0;
         pool.bump = *ctx.bumps.get("pool").unwrap();
@@ -28,21 +32,65 @@ pub mod payment_settlement {
         Ok(())
     }
 
-    /// Create a new settlement for a payment
+    /// Create the pool vault and fee ledger for an SPL mint (authority only)
+    pub fn initialize_mint_vault(ctx: Context<InitializeMintVault>) -> Result<()> {
+        let mint_fees = &mut ctx.accounts.mint_fees;
+
+        mint_fees.mint = ctx.accounts.mint.key();
+        mint_fees.total_settled = 0;
+        mint_fees.total_fees_collected = 0;
+        mint_fees.total_fees_withdrawn = 0;
+        mint_fees.bump = *ctx.bumps.get("mint_fees").unwrap();
+
+        msg!("Pool vault created for mint {}", mint_fees.mint);
+
+        Ok(())
+    }
+
+    /// Create a new settlement for a payment.
+    /// `mint` makes it a token settlement, with `amount` in the mint's base units.
+    /// The fee rate is the recipient's override if one is set, otherwise the tier
+    /// of the mint's fee schedule for the recipient's rolling 30-day processed
+    /// volume in that mint, otherwise the pool rate.
     pub fn create_settlement(
         ctx: Context<CreateSettlement>,
         amount: u64,
         recipient: Pubkey,
         payment_id: String,
+        mint: Option<Pubkey>,
     ) -> Result<()> {
         let settlement = &mut ctx.accounts.settlement;
         let pool = &mut ctx.accounts.pool;
+        let merchant_volume = &mut ctx.accounts.merchant_volume;
         let clock = Clock::get()?;
 
         require!(amount > 0, SettlementError::InvalidAmount);
 
+        if merchant_volume.merchant == Pubkey::default() {
+            merchant_volume.merchant = recipient;
+            merchant_volume.mint = mint;
+            merchant_volume.bump = *ctx.bumps.get("merchant_volume").unwrap();
+        }
+        let volume = merchant_volume.rolling_volume(clock.unix_timestamp);
+
+        let fee_override = load_if_initialized::<MerchantFeeOverride>(
+            &ctx.accounts.fee_override,
+            ctx.program_id,
+        )?;
+        let fee_schedule =
+            load_if_initialized::<FeeSchedule>(&ctx.accounts.fee_schedule, ctx.program_id)?;
+
+        let (fee_bps, fee_source) = match (fee_override, fee_schedule) {
+            (Some(fee_override), _) => (fee_override.fee_bps, FeeSource::MerchantOverride),
+            (None, Some(schedule)) => match schedule.tier_for(volume) {
+                Some((index, tier)) => (tier.fee_bps, FeeSource::Tier(index)),
+                None => (pool.fee_percentage, FeeSource::Flat),
+            },
+            (None, None) => (pool.fee_percentage, FeeSource::Flat),
+        };
+
         // Calculate fee
-        let fee = (amount as u128 * pool.fee_percentage as u128 / 10000) as u64;
+        let fee = (amount as u128 * fee_bps as u128 / 10000) as u64;
         let net_amount = amount.checked_sub(fee).ok_or(SettlementError::InvalidAmount)?;
 
         settlement.payment_id = payment_id.clone();
@@ -51,6 +99,9 @@ pub mod payment_settlement {
         settlement.gross_amount = amount;
         settlement.fee_amount = fee;
         settlement.net_amount = net_amount;
+        settlement.fee_bps = fee_bps;
+        settlement.fee_source = fee_source.clone();
+        settlement.mint = mint;
         settlement.status = SettlementStatus::Pending;
         settlement.created_at = clock.unix_timestamp;
         settlement.settled_at = None;
@@ -58,19 +109,27 @@ pub mod payment_settlement {
 
         pool.active_settlements += 1;
 
+        // Token settlements are denominated in the mint's base units
+        let unit = if mint.is_some() { "base units" } else { "lamports" };
+
         msg!("Settlement created:");
         msg!("  Payment ID: {}", payment_id);
-        msg!("  Gross: {} lamports", amount);
-        msg!("  Fee: {} lamports", fee);
-        msg!("  Net: {} lamports", net_amount);
+        msg!("  Gross: {} {}", amount, unit);
+        msg!("  Fee: {} {} ({} bps, {:?})", fee, unit, fee_bps, fee_source);
+        msg!("  Net: {} {}", net_amount, unit);
+        if let Some(mint) = mint {
+            msg!("  Mint: {}", mint);
+        }
 
         Ok(())
     }
 
-    /// Process the settlement and transfer funds
+    /// Process the settlement and transfer funds.
+    /// Token settlements move the gross amount into the pool vault for the mint
+    /// and pay the net amount to the recipient's associated token account.
+    /// The gross amount counts toward the recipient's volume once processed.
     pub fn process_settlement(ctx: Context<ProcessSettlement>) -> Result<()> {
         let settlement = &mut ctx.accounts.settlement;
-        let pool = &mut ctx.accounts.pool;
         let clock = Clock::get()?;
 
         require!(
@@ -83,55 +142,207 @@ pub mod payment_settlement {
             SettlementError::Unauthorized
         );
 
-        // Transfer gross amount from payer to settlement pool
-        let transfer_to_pool = system_instruction::transfer(
-            &ctx.accounts.payer.key(),
-            &ctx.accounts.pool.key(),
-            settlement.gross_amount,
+        require!(
+            ctx.accounts.recipient.key() == settlement.recipient,
+            SettlementError::InvalidRecipient
         );
 
-        anchor_lang::solana_program::program::invoke(
-            &transfer_to_pool,
-            &[
-                ctx.accounts.payer.to_account_info(),
-                ctx.accounts.pool.to_account_info(),
-            ],
-        )?;
-
-        // Transfer net amount from pool to recipient
-        **ctx.accounts.pool.to_account_info().try_borrow_mut_lamports()? -= settlement.net_amount;
-        **ctx.accounts.recipient.try_borrow_mut_lamports()? += settlement.net_amount;
+        match settlement.mint {
+            None => {
+                // Transfer gross amount from payer to settlement pool
+                let transfer_to_pool = system_instruction::transfer(
+                    &ctx.accounts.payer.key(),
+                    &ctx.accounts.pool.key(),
+                    settlement.gross_amount,
+                );
+
+                anchor_lang::solana_program::program::invoke(
+                    &transfer_to_pool,
+                    &[
+                        ctx.accounts.payer.to_account_info(),
+                        ctx.accounts.pool.to_account_info(),
+                    ],
+                )?;
+
+                // Transfer net amount from pool to recipient
+                **ctx.accounts.pool.to_account_info().try_borrow_mut_lamports()? -=
+                    settlement.net_amount;
+                **ctx.accounts.recipient.try_borrow_mut_lamports()? += settlement.net_amount;
+
+                let pool = &mut ctx.accounts.pool;
+                pool.total_settled += settlement.net_amount;
+                pool.total_fees_collected += settlement.fee_amount;
+            }
+            Some(mint) => {
+                let payer_token = ctx
+                    .accounts
+                    .payer_token
+                    .as_ref()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+                let pool_vault = ctx
+                    .accounts
+                    .pool_vault
+                    .as_ref()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+                let recipient_token = ctx
+                    .accounts
+                    .recipient_token
+                    .as_ref()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+
+                require!(
+                    pool_vault.owner == ctx.accounts.pool.key() && pool_vault.mint == mint,
+                    SettlementError::InvalidVault
+                );
+                require!(
+                    recipient_token.key()
+                        == get_associated_token_address(&settlement.recipient, &mint),
+                    SettlementError::InvalidRecipient
+                );
+
+                // Transfer gross amount from payer to the pool vault
+                token::transfer(
+                    CpiContext::new(
+                        token_program.to_account_info(),
+                        Transfer {
+                            from: payer_token.to_account_info(),
+                            to: pool_vault.to_account_info(),
+                            authority: ctx.accounts.payer.to_account_info(),
+                        },
+                    ),
+                    settlement.gross_amount,
+                )?;
+
+                // Transfer net amount from the vault to the recipient
+                let bump = [ctx.accounts.pool.bump];
+                let seeds: &[&[u8]] = &[b"pool", &bump];
+
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        Transfer {
+                            from: pool_vault.to_account_info(),
+                            to: recipient_token.to_account_info(),
+                            authority: ctx.accounts.pool.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    settlement.net_amount,
+                )?;
+
+                let mint_fees = ctx
+                    .accounts
+                    .mint_fees
+                    .as_mut()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+                require!(mint_fees.mint == mint, SettlementError::InvalidMint);
+
+                mint_fees.total_settled += settlement.net_amount;
+                mint_fees.total_fees_collected += settlement.fee_amount;
+            }
+        }
 
         settlement.status = SettlementStatus::Completed;
         settlement.settled_at = Some(clock.unix_timestamp);
 
-        pool.total_settled += settlement.net_amount;
-        pool.total_fees_collected += settlement.fee_amount;
-        pool.active_settlements -= 1;
+        ctx.accounts
+            .merchant_volume
+            .record(clock.unix_timestamp, settlement.gross_amount);
+
+        ctx.accounts.pool.active_settlements -= 1;
 
         msg!("Settlement processed:");
-        msg!("  Transferred {} lamports to recipient", settlement.net_amount);
-        msg!("  Fee collected: {} lamports", settlement.fee_amount);
+        msg!("  Transferred {} to recipient", settlement.net_amount);
+        msg!("  Fee collected: {}", settlement.fee_amount);
 
         Ok(())
     }
 
-    /// Batch process multiple settlements
-    pub fn batch_process_settlements(
-        ctx: Context<BatchProcessSettlements>,
+    /// Batch process multiple SOL settlements of the signing payer.
+    /// Remaining accounts are `settlement_count` triples of
+    /// `[settlement, recipient, merchant_volume]`.
+    /// In `AllOrNothing` mode any invalid entry fails the batch; in `SkipInvalid`
+    /// mode it is logged and skipped.
+    pub fn batch_process_settlements<'info>(
+        ctx: Context<'_, '_, '_, 'info, BatchProcessSettlements<'info>>,
         settlement_count: u8,
+        mode: BatchMode,
     ) -> Result<()> {
         require!(settlement_count > 0, SettlementError::InvalidBatchSize);
         require!(settlement_count <= 10, SettlementError::InvalidBatchSize); // Max 10 per batch
+        require!(
+            ctx.remaining_accounts.len() == settlement_count as usize * 3,
+            SettlementError::InvalidBatchSize
+        );
 
-        let pool = &mut ctx.accounts.pool;
+        let payer = ctx.accounts.payer.to_account_info();
+        let pool_info = ctx.accounts.pool.to_account_info();
+        let clock = Clock::get()?;
 
-        msg!("Batch processing {} settlements", settlement_count);
+        msg!("Batch processing {} settlements ({:?})", settlement_count, mode);
+
+        let mut processed: u8 = 0;
+        let mut total_net: u64 = 0;
+        let mut total_fees: u64 = 0;
+
+        for (index, entry) in ctx.remaining_accounts.chunks(3).enumerate() {
+            let (settlement_info, recipient_info, volume_info) = (&entry[0], &entry[1], &entry[2]);
+
+            let (mut settlement, mut merchant_volume) = match load_batch_entry(
+                settlement_info,
+                recipient_info,
+                volume_info,
+                &payer,
+                ctx.program_id,
+            ) {
+                Ok(entry) => entry,
+                Err(err) if mode == BatchMode::SkipInvalid => {
+                    msg!("  Skipping entry {}: {:?}", index, err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            // Transfer gross amount from payer to settlement pool
+            anchor_lang::solana_program::program::invoke(
+                &system_instruction::transfer(
+                    payer.key,
+                    pool_info.key,
+                    settlement.gross_amount,
+                ),
+                &[payer.clone(), pool_info.clone()],
+            )?;
+
+            // Transfer net amount from pool to recipient
+            **pool_info.try_borrow_mut_lamports()? -= settlement.net_amount;
+            **recipient_info.try_borrow_mut_lamports()? += settlement.net_amount;
+
+            settlement.status = SettlementStatus::Completed;
+            settlement.settled_at = Some(clock.unix_timestamp);
+            settlement.exit(ctx.program_id)?;
+
+            merchant_volume.record(clock.unix_timestamp, settlement.gross_amount);
+            merchant_volume.exit(ctx.program_id)?;
+
+            total_net += settlement.net_amount;
+            total_fees += settlement.fee_amount;
+            processed += 1;
+        }
 
-        // Note: In a real implementation, you would iterate through remaining accounts
-        // and process each settlement. This is a simplified version.
+        let pool = &mut ctx.accounts.pool;
+        pool.total_settled += total_net;
+        pool.total_fees_collected += total_fees;
+        pool.active_settlements = pool.active_settlements.saturating_sub(processed as u64);
 
-        pool.active_settlements = pool.active_settlements.saturating_sub(settlement_count as u64);
+        msg!("Batch processed:");
+        msg!("  Settlements: {}/{}", processed, settlement_count);
+        msg!("  Transferred {} lamports to recipients", total_net);
+        msg!("  Fees collected: {} lamports", total_fees);
 
         Ok(())
     }
@@ -161,10 +372,127 @@ pub mod payment_settlement {
         Ok(())
     }
 
-    /// Withdraw collected fees (authority only)
+    /// Withdraw collected fees (authority only).
+    /// With `mint` set, withdraws that mint's fees from its pool vault.
+    /// Withdrawals are capped at collected minus already withdrawn fees, the pool
+    /// always stays rent-exempt, and fees go to `fee_destination` when one is set.
     pub fn withdraw_fees(
         ctx: Context<WithdrawFees>,
         amount: u64,
+        mint: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.pool.authority,
+            SettlementError::Unauthorized
+        );
+
+        require!(amount > 0, SettlementError::InvalidAmount);
+
+        let beneficiary = ctx
+            .accounts
+            .pool
+            .fee_destination
+            .unwrap_or(ctx.accounts.pool.authority);
+
+        match mint {
+            None => {
+                let pool = &ctx.accounts.pool;
+                require!(
+                    amount <= pool.total_fees_collected - pool.total_fees_withdrawn,
+                    SettlementError::ExceedsAvailableFees
+                );
+
+                let pool_info = pool.to_account_info();
+                let rent_exempt = Rent::get()?.minimum_balance(pool_info.data_len());
+                require!(
+                    pool_info.lamports().saturating_sub(amount) >= rent_exempt,
+                    SettlementError::InsufficientFunds
+                );
+
+                let destination = match &ctx.accounts.fee_destination {
+                    Some(destination) => destination.to_account_info(),
+                    None => ctx.accounts.authority.to_account_info(),
+                };
+                require!(
+                    destination.key() == beneficiary,
+                    SettlementError::InvalidFeeDestination
+                );
+
+                // Transfer fees from pool to the fee destination
+                **pool_info.try_borrow_mut_lamports()? -= amount;
+                **destination.try_borrow_mut_lamports()? += amount;
+
+                ctx.accounts.pool.total_fees_withdrawn += amount;
+
+                msg!("Withdrew {} lamports in fees to {}", amount, beneficiary);
+            }
+            Some(mint) => {
+                let mint_fees = ctx
+                    .accounts
+                    .mint_fees
+                    .as_mut()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+                let pool_vault = ctx
+                    .accounts
+                    .pool_vault
+                    .as_ref()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+                let destination_token = ctx
+                    .accounts
+                    .destination_token
+                    .as_ref()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(SettlementError::MissingTokenAccounts)?;
+
+                require!(mint_fees.mint == mint, SettlementError::InvalidMint);
+                require!(
+                    amount <= mint_fees.total_fees_collected - mint_fees.total_fees_withdrawn,
+                    SettlementError::ExceedsAvailableFees
+                );
+                require!(
+                    pool_vault.owner == ctx.accounts.pool.key() && pool_vault.mint == mint,
+                    SettlementError::InvalidVault
+                );
+                require!(destination_token.mint == mint, SettlementError::InvalidMint);
+                require!(
+                    destination_token.owner == beneficiary,
+                    SettlementError::InvalidFeeDestination
+                );
+
+                let bump = [ctx.accounts.pool.bump];
+                let seeds: &[&[u8]] = &[b"pool", &bump];
+
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        Transfer {
+                            from: pool_vault.to_account_info(),
+                            to: destination_token.to_account_info(),
+                            authority: ctx.accounts.pool.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    amount,
+                )?;
+
+                mint_fees.total_fees_withdrawn += amount;
+
+                msg!("Withdrew {} of mint {} in fees to {}", amount, mint, beneficiary);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set or clear the account that receives withdrawn fees (authority only).
+    /// When unset, fees go to the authority.
+    pub fn set_fee_destination(
+        ctx: Context<SetFeeDestination>,
+        fee_destination: Option<Pubkey>,
     ) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
 
@@ -173,13 +501,73 @@ pub mod payment_settlement {
             SettlementError::Unauthorized
         );
 
-        require!(amount > 0, SettlementError::InvalidAmount);
+        pool.fee_destination = fee_destination;
+
+        match fee_destination {
+            Some(destination) => msg!("Fee destination set to {}", destination),
+            None => msg!("Fee destination cleared"),
+        }
+
+        Ok(())
+    }
+
+    /// Create or replace the volume-tiered fee schedule for `mint` (authority only).
+    /// Thresholds are in that mint's base units (lamports for `None`). Tiers must
+    /// have strictly increasing `min_volume`; an empty schedule falls back to the
+    /// pool's flat fee.
+    pub fn set_fee_schedule(
+        ctx: Context<SetFeeSchedule>,
+        mint: Option<Pubkey>,
+        tiers: Vec<FeeTier>,
+    ) -> Result<()> {
+        let fee_schedule = &mut ctx.accounts.fee_schedule;
+
+        require!(tiers.len() <= 5, SettlementError::InvalidFeeSchedule);
+        for (i, tier) in tiers.iter().enumerate() {
+            require!(tier.fee_bps <= 1000, SettlementError::InvalidFeePercentage); // Max 10%
+            if i > 0 {
+                require!(
+                    tier.min_volume > tiers[i - 1].min_volume,
+                    SettlementError::InvalidFeeSchedule
+                );
+            }
+        }
+
+        fee_schedule.mint = mint;
+        fee_schedule.tiers = tiers;
+        fee_schedule.bump = *ctx.bumps.get("fee_schedule").unwrap();
+
+        msg!("Fee schedule updated with {} tiers", fee_schedule.tiers.len());
+        if let Some(mint) = mint {
+            msg!("  Mint: {}", mint);
+        }
+
+        Ok(())
+    }
+
+    /// Set a merchant-specific fee rate that takes precedence over the schedule
+    /// (authority only)
+    pub fn set_merchant_fee_override(
+        ctx: Context<SetMerchantFeeOverride>,
+        merchant: Pubkey,
+        fee_bps: u16,
+    ) -> Result<()> {
+        let fee_override = &mut ctx.accounts.fee_override;
+
+        require!(fee_bps <= 1000, SettlementError::InvalidFeePercentage); // Max 10%
 
-        // Transfer fees from pool to authority
-        **ctx.accounts.pool.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.authority.try_borrow_mut_lamports()? += amount;
+        fee_override.merchant = merchant;
+        fee_override.fee_bps = fee_bps;
+        fee_override.bump = *ctx.bumps.get("fee_override").unwrap();
 
-        msg!("Withdrew {} lamports in fees", amount);
+        msg!("Fee override for {}: {}%", merchant, fee_bps as f64 / 100.0);
+
+        Ok(())
+    }
+
+    /// Remove a merchant fee override (authority only)
+    pub fn remove_merchant_fee_override(ctx: Context<RemoveMerchantFeeOverride>) -> Result<()> {
+        msg!("Fee override removed for {}", ctx.accounts.fee_override.merchant);
 
         Ok(())
     }
@@ -215,6 +603,7 @@ pub mod payment_settlement {
         msg!("Settlement Pool Statistics:");
         msg!("  Total settled: {} lamports", pool.total_settled);
         msg!("  Total fees collected: {} lamports", pool.total_fees_collected);
+        msg!("  Total fees withdrawn: {} lamports", pool.total_fees_withdrawn);
         msg!("  Active settlements: {}", pool.active_settlements);
         msg!("  Fee percentage: {}%", pool.fee_percentage as f64 / 100.0);
 
@@ -222,6 +611,85 @@ pub mod payment_settlement {
     }
 }
 
+/// Deserialize an optional program account passed at its PDA address, or `None`
+/// if it has not been created
+fn load_if_initialized<T: AccountDeserialize>(
+    info: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<Option<T>> {
+    if info.owner != program_id || info.data_is_empty() {
+        return Ok(None);
+    }
+
+    let data = info.try_borrow_data()?;
+    Ok(Some(T::try_deserialize(&mut &data[..])?))
+}
+
+/// Load a `[settlement, recipient, merchant_volume]` batch entry and check that it
+/// can be processed
+fn load_batch_entry<'info>(
+    settlement_info: &AccountInfo<'info>,
+    recipient_info: &AccountInfo<'info>,
+    volume_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<(Account<'info, Settlement>, Account<'info, MerchantVolume>)> {
+    let settlement = Account::<Settlement>::try_from(settlement_info)?;
+
+    let expected = Pubkey::create_program_address(
+        &[
+            b"settlement",
+            settlement.payer.as_ref(),
+            settlement.payment_id.as_bytes(),
+            &[settlement.bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| SettlementError::InvalidSettlementAccount)?;
+    require!(
+        settlement_info.key() == expected && settlement_info.is_writable,
+        SettlementError::InvalidSettlementAccount
+    );
+
+    require!(settlement.payer == payer.key(), SettlementError::Unauthorized);
+
+    // Token settlements need per-entry token accounts; use `process_settlement`
+    require!(settlement.mint.is_none(), SettlementError::InvalidMint);
+
+    require!(
+        settlement.status == SettlementStatus::Pending,
+        SettlementError::InvalidSettlementStatus
+    );
+
+    require!(
+        recipient_info.key() == settlement.recipient && recipient_info.is_writable,
+        SettlementError::InvalidRecipient
+    );
+
+    require!(
+        payer.lamports() >= settlement.gross_amount,
+        SettlementError::InsufficientFunds
+    );
+
+    let merchant_volume = Account::<MerchantVolume>::try_from(volume_info)?;
+    let expected_volume = Pubkey::create_program_address(
+        &[
+            b"merchant_volume",
+            settlement.recipient.as_ref(),
+            Pubkey::default().as_ref(),
+            &[merchant_volume.bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| SettlementError::InvalidMerchantVolume)?;
+    require!(
+        volume_info.key() == expected_volume && volume_info.is_writable,
+        SettlementError::InvalidMerchantVolume
+    );
+
+    Ok((settlement, merchant_volume))
+}
+
 #[derive(Accounts)]
 pub struct InitializePool<'info> {
     #[account(
@@ -240,7 +708,47 @@ pub struct InitializePool<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(payment_id: String)]
+pub struct InitializeMintVault<'info> {
+    #[account(
+        seeds = [b"pool"],
+        bump = pool.bump,
+        has_one = authority @ SettlementError::Unauthorized,
+    )]
+    pub pool: Account<'info, SettlementPool>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = pool,
+        seeds = [b"pool_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub pool_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MintFees::INIT_SPACE,
+        seeds = [b"mint_fees", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_fees: Account<'info, MintFees>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, recipient: Pubkey, payment_id: String, mint: Option<Pubkey>)]
 pub struct CreateSettlement<'info> {
     #[account(
         init,
@@ -258,6 +766,23 @@ pub struct CreateSettlement<'info> {
     )]
     pub pool: Account<'info, SettlementPool>,
 
+    /// CHECK: Fee schedule PDA for the settlement mint; read only if it has been created
+    #[account(seeds = [b"fee_schedule", mint.unwrap_or_default().as_ref()], bump)]
+    pub fee_schedule: UncheckedAccount<'info>,
+
+    /// CHECK: Recipient's fee override PDA; read only if it has been created
+    #[account(seeds = [b"fee_override", recipient.as_ref()], bump)]
+    pub fee_override: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + MerchantVolume::INIT_SPACE,
+        seeds = [b"merchant_volume", recipient.as_ref(), mint.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub merchant_volume: Account<'info, MerchantVolume>,
+
     #[account(mut)]
     pub payer: Signer<'info>,
 
@@ -283,6 +808,43 @@ pub struct ProcessSettlement<'info> {
     #[account(mut)]
     pub recipient: AccountInfo<'info>,
 
+    /// Payer's token account (token settlements only)
+    #[account(mut)]
+    pub payer_token: Option<Account<'info, TokenAccount>>,
+
+    /// Pool vault for the settlement mint (token settlements only)
+    #[account(
+        mut,
+        seeds = [b"pool_vault", settlement.mint.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub pool_vault: Option<Account<'info, TokenAccount>>,
+
+    /// Recipient's associated token account (token settlements only)
+    #[account(mut)]
+    pub recipient_token: Option<Account<'info, TokenAccount>>,
+
+    /// Fee ledger for the settlement mint (token settlements only)
+    #[account(
+        mut,
+        seeds = [b"mint_fees", settlement.mint.unwrap_or_default().as_ref()],
+        bump = mint_fees.bump
+    )]
+    pub mint_fees: Option<Account<'info, MintFees>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"merchant_volume",
+            settlement.recipient.as_ref(),
+            settlement.mint.unwrap_or_default().as_ref()
+        ],
+        bump = merchant_volume.bump
+    )]
+    pub merchant_volume: Account<'info, MerchantVolume>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -295,7 +857,11 @@ pub struct BatchProcessSettlements<'info> {
     )]
     pub pool: Account<'info, SettlementPool>,
 
-    pub authority: Signer<'info>,
+    /// Payer of every settlement in the batch
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -314,6 +880,7 @@ pub struct CancelSettlement<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(amount: u64, mint: Option<Pubkey>)]
 pub struct WithdrawFees<'info> {
     #[account(
         mut,
@@ -324,6 +891,115 @@ pub struct WithdrawFees<'info> {
 
     #[account(mut)]
     pub authority: Signer<'info>,
+
+    /// CHECK: Must match `pool.fee_destination` (SOL withdrawals with a destination set)
+    #[account(mut)]
+    pub fee_destination: Option<UncheckedAccount<'info>>,
+
+    /// Fee ledger for the withdrawn mint (token withdrawals only)
+    #[account(
+        mut,
+        seeds = [b"mint_fees", mint.unwrap_or_default().as_ref()],
+        bump = mint_fees.bump
+    )]
+    pub mint_fees: Option<Account<'info, MintFees>>,
+
+    /// Pool vault for the withdrawn mint (token withdrawals only)
+    #[account(
+        mut,
+        seeds = [b"pool_vault", mint.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub pool_vault: Option<Account<'info, TokenAccount>>,
+
+    /// Token account receiving the fees (token withdrawals only)
+    #[account(mut)]
+    pub destination_token: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeDestination<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, SettlementPool>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Option<Pubkey>)]
+pub struct SetFeeSchedule<'info> {
+    #[account(
+        seeds = [b"pool"],
+        bump = pool.bump,
+        has_one = authority @ SettlementError::Unauthorized,
+    )]
+    pub pool: Account<'info, SettlementPool>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + FeeSchedule::INIT_SPACE,
+        seeds = [b"fee_schedule", mint.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(merchant: Pubkey)]
+pub struct SetMerchantFeeOverride<'info> {
+    #[account(
+        seeds = [b"pool"],
+        bump = pool.bump,
+        has_one = authority @ SettlementError::Unauthorized,
+    )]
+    pub pool: Account<'info, SettlementPool>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MerchantFeeOverride::INIT_SPACE,
+        seeds = [b"fee_override", merchant.as_ref()],
+        bump
+    )]
+    pub fee_override: Account<'info, MerchantFeeOverride>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveMerchantFeeOverride<'info> {
+    #[account(
+        seeds = [b"pool"],
+        bump = pool.bump,
+        has_one = authority @ SettlementError::Unauthorized,
+    )]
+    pub pool: Account<'info, SettlementPool>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"fee_override", fee_override.merchant.as_ref()],
+        bump = fee_override.bump
+    )]
+    pub fee_override: Account<'info, MerchantFeeOverride>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -349,6 +1025,8 @@ pub struct SettlementPool {
     pub authority: Pubkey,           // 32 bytes
     pub total_settled: u64,          // 8 bytes
     pub total_fees_collected: u64,   // 8 bytes
+    pub total_fees_withdrawn: u64,   // 8 bytes
+    pub fee_destination: Option<Pubkey>, // 33 bytes (None = authority)
     pub fee_percentage: u16,         // 2 bytes (basis points)
     pub active_settlements: u64,     // 8 bytes
     pub bump: u8,                    // 1 byte
@@ -364,12 +1042,113 @@ pub struct Settlement {
     pub gross_amount: u64,           // 8 bytes
     pub fee_amount: u64,             // 8 bytes
     pub net_amount: u64,             // 8 bytes
+    pub fee_bps: u16,                // 2 bytes (rate applied)
+    pub fee_source: FeeSource,       // 2 bytes (where the rate came from)
+    pub mint: Option<Pubkey>,        // 33 bytes (None = SOL)
     pub status: SettlementStatus,    // 1 byte
     pub created_at: i64,             // 8 bytes
     pub settled_at: Option<i64>,     // 9 bytes
     pub bump: u8,                    // 1 byte
 }
 
+/// Volume-tiered fee rates for one currency, keyed on a merchant's rolling
+/// 30-day volume in it
+#[account]
+#[derive(InitSpace)]
+pub struct FeeSchedule {
+    pub mint: Option<Pubkey>,        // 33 bytes (None = SOL)
+    #[max_len(5)]
+    pub tiers: Vec<FeeTier>,         // 4 + (10 * 5) bytes
+    pub bump: u8,                    // 1 byte
+}
+
+impl FeeSchedule {
+    /// Highest tier whose threshold `volume` has reached
+    pub fn tier_for(&self, volume: u64) -> Option<(u8, &FeeTier)> {
+        self.tiers
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, tier)| volume >= tier.min_volume)
+            .map(|(index, tier)| (index as u8, tier))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct FeeTier {
+    pub min_volume: u64,             // 8 bytes (base units of the schedule's mint)
+    pub fee_bps: u16,                // 2 bytes (basis points)
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantFeeOverride {
+    pub merchant: Pubkey,            // 32 bytes
+    pub fee_bps: u16,                // 2 bytes (basis points)
+    pub bump: u8,                    // 1 byte
+}
+
+/// A merchant's processed settlement volume in one currency, bucketed by day so
+/// the last 30 days can be summed as a rolling window
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantVolume {
+    pub merchant: Pubkey,            // 32 bytes
+    pub mint: Option<Pubkey>,        // 33 bytes (None = SOL)
+    pub daily_volume: [u64; 30],     // 8 * 30 bytes (indexed by day % 30)
+    pub last_day: i64,               // 8 bytes (day of the latest recorded volume)
+    pub bump: u8,                    // 1 byte
+}
+
+impl MerchantVolume {
+    pub const WINDOW_DAYS: i64 = 30;
+    pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+    /// Volume over the 30 days ending on the day of `now`
+    pub fn rolling_volume(&self, now: i64) -> u64 {
+        let today = now / Self::SECONDS_PER_DAY;
+
+        (0..Self::WINDOW_DAYS)
+            .map(|offset| self.last_day - offset)
+            .filter(|day| *day >= 0 && today - day < Self::WINDOW_DAYS)
+            .fold(0u64, |volume, day| {
+                volume.saturating_add(self.daily_volume[Self::bucket(day)])
+            })
+    }
+
+    /// Add `amount` to today's bucket, clearing buckets of days that have
+    /// left the window since the last recorded volume
+    fn record(&mut self, now: i64, amount: u64) {
+        let today = now / Self::SECONDS_PER_DAY;
+
+        if today > self.last_day {
+            let stale_days = (today - self.last_day).min(Self::WINDOW_DAYS);
+            for day in (today - stale_days + 1)..=today {
+                self.daily_volume[Self::bucket(day)] = 0;
+            }
+            self.last_day = today;
+        }
+
+        let bucket = &mut self.daily_volume[Self::bucket(today)];
+        *bucket = bucket.saturating_add(amount);
+    }
+
+    fn bucket(day: i64) -> usize {
+        (day % Self::WINDOW_DAYS) as usize
+    }
+}
+
+/// Per-mint settlement totals for token settlements
+#[account]
+#[derive(InitSpace)]
+pub struct MintFees {
+    pub mint: Pubkey,                // 32 bytes
+    pub total_settled: u64,          // 8 bytes
+    pub total_fees_collected: u64,   // 8 bytes
+    pub total_fees_withdrawn: u64,   // 8 bytes
+    pub bump: u8,                    // 1 byte
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum SettlementStatus {
     Pending,
@@ -378,6 +1157,24 @@ pub enum SettlementStatus {
     Failed,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Debug)]
+pub enum FeeSource {
+    /// Pool's flat `fee_percentage`
+    Flat,
+    /// Index into the fee schedule tiers
+    Tier(u8),
+    /// Merchant-specific override
+    MerchantOverride,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BatchMode {
+    /// Any invalid entry fails the whole batch
+    AllOrNothing,
+    /// Invalid entries are skipped
+    SkipInvalid,
+}
+
 #[error_code]
 pub enum SettlementError {
     #[msg("Invalid amount")]
@@ -397,4 +1194,31 @@ pub enum SettlementError {
 
     #[msg("Insufficient funds")]
     InsufficientFunds,
+
+    #[msg("Invalid settlement account")]
+    InvalidSettlementAccount,
+
+    #[msg("Recipient does not match settlement")]
+    InvalidRecipient,
+
+    #[msg("Missing token accounts")]
+    MissingTokenAccounts,
+
+    #[msg("Invalid mint")]
+    InvalidMint,
+
+    #[msg("Invalid pool vault")]
+    InvalidVault,
+
+    #[msg("Amount exceeds available fees")]
+    ExceedsAvailableFees,
+
+    #[msg("Invalid fee destination")]
+    InvalidFeeDestination,
+
+    #[msg("Invalid fee schedule")]
+    InvalidFeeSchedule,
+
+    #[msg("Invalid merchant volume account")]
+    InvalidMerchantVolume,
 }
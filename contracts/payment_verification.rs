@@ -1,5 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::{
+    ed25519_program, keccak, system_instruction, system_program, sysvar,
+};
+use anchor_spl::token::{self, TokenAccount};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -14,6 +21,13 @@ pub mod payment_verification {
         verifier.authority = ctx.accounts.authority.key();
         verifier.total_verifications = 0;
         verifier.total_volume = 0;
+        verifier.facilitators = Vec::new();
+        verifier.networks = Vec::new();
+        verifier.committee = Vec::new();
+        verifier.quorum = 0;
+        verifier.dispute_window_seconds = 7 * 24 * 60 * 60;
+        verifier.resolution_deadline_seconds = 14 * 24 * 60 * 60;
+        verifier.default_resolution = DisputeResolution::ApprovePayment;
         verifier.bump = *ctx.bumps.get("verifier").unwrap();
 
         msg!("Payment verifier initialized");
@@ -21,45 +35,429 @@ pub mod payment_verification {
         Ok(())
     }
 
-    /// Verify a payment proof and record it on-chain
-    pub fn verify_payment(
-        ctx: Context<VerifyPayment>,
-        payment_proof: String,
-        amount: u64,
-        recipient: Pubkey,
-        network: String,
-        transaction_signature: String,
-    ) -> Result<()> {
+    /// Verify a payment proof and record it on-chain.
+    /// The record lives at a PDA derived from `payment_hash` (keccak256 of the proof),
+    /// so proofs of any length up to 512 bytes can be recorded.
+    /// In `Reported` mode the previous instruction must be an Ed25519 attestation by a
+    /// registered facilitator over `attestation_message(...)`.
+    /// In `Atomic` mode the instruction immediately before must be a System or SPL
+    /// token transfer of at least `amount` from the payer to `recipient`, so each
+    /// transfer backs at most one recorded payment.
+    /// The x402 `validAfter`/`validBefore` window is checked against the clock, and the
+    /// payer's `nonce` is recorded so the same authorization cannot be recorded twice.
+    /// `network` must be enabled on the verifier and `asset` one of its accepted
+    /// assets (`Pubkey::default()` for the native coin).
+    /// `resource_hash` and `request_hash` bind the record to what was bought
+    /// (keccak256 of the x402 resource URL and of the request).
+    pub fn verify_payment(ctx: Context<VerifyPayment>, args: VerifyPaymentArgs) -> Result<()> {
+        let VerifyPaymentArgs {
+            payment_hash,
+            amount,
+            recipient,
+            network,
+            asset,
+            valid_after,
+            valid_before,
+            nonce,
+            ..
+        } = args;
+
         let payment = &mut ctx.accounts.payment;
         let verifier = &mut ctx.accounts.verifier;
         let clock = Clock::get()?;
 
         require!(amount > 0, VerificationError::InvalidAmount);
+        require!(args.payment_proof.len() <= 512, VerificationError::PaymentProofTooLong);
+        if let Some(description) = &args.description {
+            require!(description.len() <= 128, VerificationError::DescriptionTooLong);
+        }
+
+        // The PDA seed hash must be the hash of the submitted proof
+        require!(
+            hash_payment_proof(&args.payment_proof) == payment_hash,
+            VerificationError::InvalidPaymentHash
+        );
+
+        let network_config = verifier
+            .network_config(network)
+            .ok_or(VerificationError::UnsupportedNetwork)?;
+        require!(network_config.enabled, VerificationError::NetworkDisabled);
+        require!(
+            network_config.accepted_assets.contains(&asset),
+            VerificationError::UnsupportedAsset
+        );
+        require!(
+            amount >= network_config.min_amount,
+            VerificationError::AmountBelowMinimum
+        );
+
+        require!(valid_after < valid_before, VerificationError::InvalidValidityWindow);
+        require!(
+            clock.unix_timestamp >= valid_after,
+            VerificationError::PaymentNotYetValid
+        );
+        require!(
+            clock.unix_timestamp < valid_before,
+            VerificationError::PaymentExpired
+        );
+
+        let instructions = ctx
+            .accounts
+            .instructions
+            .as_ref()
+            .ok_or(VerificationError::MissingInstructionsSysvar)?
+            .to_account_info();
+
+        let facilitator = match args.mode {
+            VerificationMode::Reported => {
+                let message = attestation_message(&args, &ctx.accounts.payer.key());
+                let facilitator = load_ed25519_signer(&instructions, &message)?;
 
-        // Generate payment hash from proof
-        let payment_hash = keccak::hash(payment_proof.as_bytes());
+                require!(
+                    verifier.facilitators.contains(&facilitator),
+                    VerificationError::UnknownFacilitator
+                );
 
-        payment.payment_hash = payment_hash.to_bytes();
-        payment.payment_proof = payment_proof.clone();
+                Some(facilitator)
+            }
+            VerificationMode::Atomic => {
+                // Only transfers on this cluster can be observed
+                require!(
+                    network.is_solana(),
+                    VerificationError::UnsupportedNetwork
+                );
+
+                let token_accounts = if asset == Pubkey::default() {
+                    None
+                } else {
+                    let payer_token = ctx
+                        .accounts
+                        .payer_token
+                        .as_ref()
+                        .ok_or(VerificationError::InvalidPayerAccount)?;
+                    require!(
+                        payer_token.owner == ctx.accounts.payer.key(),
+                        VerificationError::InvalidPayerAccount
+                    );
+                    require!(payer_token.mint == asset, VerificationError::UnsupportedAsset);
+
+                    let recipient_token = ctx
+                        .accounts
+                        .recipient_token
+                        .as_ref()
+                        .ok_or(VerificationError::InvalidRecipientAccount)?;
+                    require!(
+                        recipient_token.owner == recipient,
+                        VerificationError::InvalidRecipientAccount
+                    );
+                    require!(
+                        recipient_token.mint == asset,
+                        VerificationError::UnsupportedAsset
+                    );
+                    Some((payer_token.key(), recipient_token.key()))
+                };
+
+                require!(
+                    has_prior_transfer(
+                        &instructions,
+                        &ctx.accounts.payer.key(),
+                        &recipient,
+                        token_accounts,
+                        amount
+                    )?,
+                    VerificationError::TransferNotFound
+                );
+
+                None
+            }
+        };
+
+        consume_nonce(
+            &ctx.accounts.nonce_record,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ctx.program_id,
+            nonce,
+            payment_hash,
+            clock.unix_timestamp,
+        )?;
+
+        payment.payment_hash = payment_hash;
+        payment.payment_proof = args.payment_proof.clone();
         payment.amount = amount;
         payment.payer = ctx.accounts.payer.key();
         payment.recipient = recipient;
-        payment.network = network.clone();
-        payment.transaction_signature = transaction_signature.clone();
+        payment.network = network;
+        payment.asset = asset;
+        payment.transaction_signature = args.transaction_signature.clone();
         payment.verified_at = clock.unix_timestamp;
-        payment.status = PaymentStatus::Verified;
+        // With a committee configured the payment waits for a quorum of attestations
+        payment.status = if verifier.quorum > 0 {
+            PaymentStatus::Pending
+        } else {
+            PaymentStatus::Verified
+        };
+        payment.verification_mode = args.mode.clone();
+        payment.facilitator = facilitator;
+        payment.valid_after = valid_after;
+        payment.valid_before = valid_before;
+        payment.nonce = nonce;
+        payment.resource_hash = args.resource_hash;
+        payment.request_hash = args.request_hash;
+        payment.description = args.description;
+        payment.batch = None;
         payment.bump = *ctx.bumps.get("payment").unwrap();
 
-        // Update verifier stats
-        verifier.total_verifications += 1;
-        verifier.total_volume += amount;
+        let merchant_stats = &mut ctx.accounts.merchant_stats;
+        merchant_stats.init_once(recipient, *ctx.bumps.get("merchant_stats").unwrap());
+
+        if payment.status == PaymentStatus::Verified {
+            // Update verifier stats
+            verifier.total_verifications += 1;
+            verifier.total_volume += amount;
+            merchant_stats.record_verified(amount);
+            msg!("Payment verified:");
+        } else {
+            msg!("Payment pending committee quorum ({}):", verifier.quorum);
+        }
 
-        msg!("Payment verified:");
         msg!("  Amount: {} lamports", amount);
         msg!("  Recipient: {}", recipient);
-        msg!("  Network: {}", network);
-        msg!("  Transaction: {}", transaction_signature);
-        msg!("  Hash: {:?}", payment_hash.to_bytes());
+        msg!("  Network: {}", network.caip2());
+        msg!("  Asset: {}", asset);
+        msg!("  Transaction: {}", payment.transaction_signature);
+        msg!("  Hash: {:?}", payment_hash);
+        msg!("  Mode: {:?}", payment.verification_mode);
+
+        if let Some(facilitator) = facilitator {
+            msg!("  Attested by: {}", facilitator);
+        }
+
+        Ok(())
+    }
+
+    /// Attest a pending payment as a member of the verifier committee.
+    /// Attestations accumulate in a per-payment record; the payment moves to
+    /// `Verified` once attestations from current members reach the quorum.
+    pub fn attest_payment(ctx: Context<AttestPayment>, payment_hash: [u8; 32]) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        let attestations = &mut ctx.accounts.attestations;
+        let verifier = &mut ctx.accounts.verifier;
+        let member = ctx.accounts.member.key();
+        let clock = Clock::get()?;
+
+        require!(
+            payment.status == PaymentStatus::Pending,
+            VerificationError::InvalidPaymentStatus
+        );
+
+        require!(
+            verifier.committee.contains(&member),
+            VerificationError::NotCommitteeMember
+        );
+
+        if attestations.payment_hash != payment_hash {
+            attestations.payment_hash = payment_hash;
+            attestations.bump = *ctx.bumps.get("attestations").unwrap();
+        }
+
+        // Members removed from the committee since attesting no longer count
+        attestations
+            .attesters
+            .retain(|attester| verifier.committee.contains(attester));
+
+        require!(
+            !attestations.attesters.contains(&member),
+            VerificationError::AlreadyAttested
+        );
+        require!(
+            attestations.attesters.len() < 10,
+            VerificationError::InvalidCommittee
+        );
+
+        attestations.attesters.push(member);
+
+        msg!("Payment attested by {}", member);
+
+        tally_attestations(
+            payment,
+            Some(&mut **attestations),
+            verifier,
+            &mut ctx.accounts.merchant_stats,
+            clock.unix_timestamp,
+        );
+
+        Ok(())
+    }
+
+    /// Re-count a pending payment's attestations against the current committee and
+    /// quorum. Anyone may call it, so a payment does not stay `Pending` after the
+    /// committee changes under it: to members who have all attested already, or to
+    /// a quorum of 0. Fails with `QuorumNotReached` if the payment still falls short.
+    pub fn finalize_attestations(ctx: Context<FinalizeAttestations>) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        let clock = Clock::get()?;
+
+        require!(
+            payment.status == PaymentStatus::Pending,
+            VerificationError::InvalidPaymentStatus
+        );
+
+        let verified = tally_attestations(
+            payment,
+            ctx.accounts.attestations.as_deref_mut(),
+            &mut ctx.accounts.verifier,
+            &mut ctx.accounts.merchant_stats,
+            clock.unix_timestamp,
+        );
+        require!(verified, VerificationError::QuorumNotReached);
+
+        Ok(())
+    }
+
+    /// Record a Merkle root over many payment receipts in a single account.
+    /// Only the authority or a registered facilitator may submit batches; leaves
+    /// are `receipt_leaf(...)` and individual payments are materialized with
+    /// `prove_payment` when they need to be settled or disputed.
+    /// `payment_count` and `total_volume` are declared by the submitter and only
+    /// stored on the batch; verifier stats grow as payments are proven.
+    pub fn verify_payment_batch(
+        ctx: Context<VerifyPaymentBatch>,
+        merkle_root: [u8; 32],
+        network: Network,
+        payment_count: u32,
+        total_volume: u64,
+    ) -> Result<()> {
+        let batch = &mut ctx.accounts.batch;
+        let verifier = &ctx.accounts.verifier;
+        let submitter = ctx.accounts.submitter.key();
+        let clock = Clock::get()?;
+
+        require!(
+            submitter == verifier.authority || verifier.facilitators.contains(&submitter),
+            VerificationError::Unauthorized
+        );
+
+        require!(payment_count > 0, VerificationError::InvalidBatch);
+        require!(total_volume > 0, VerificationError::InvalidAmount);
+
+        let network_config = verifier
+            .network_config(network)
+            .ok_or(VerificationError::UnsupportedNetwork)?;
+        require!(network_config.enabled, VerificationError::NetworkDisabled);
+
+        batch.submitter = submitter;
+        batch.merkle_root = merkle_root;
+        batch.network = network;
+        batch.payment_count = payment_count;
+        batch.total_volume = total_volume;
+        batch.proven_count = 0;
+        batch.created_at = clock.unix_timestamp;
+        batch.bump = *ctx.bumps.get("batch").unwrap();
+
+        msg!("Payment batch verified:");
+        msg!("  Root: {:?}", merkle_root);
+        msg!("  Network: {}", network.caip2());
+        msg!("  Declared payments: {}", payment_count);
+        msg!("  Declared volume: {}", total_volume);
+
+        Ok(())
+    }
+
+    /// Materialize a batched payment as a `Payment` record by proving its
+    /// receipt is included in the batch root. The record can then be settled
+    /// or disputed like any other verified payment. Verifier and merchant stats
+    /// count the payment here, once its inclusion is proven. With a committee
+    /// configured the record starts `Pending` and needs a quorum of attestations.
+    /// Like `verify_payment`, the batch's network must still be enabled and the
+    /// receipt's asset and amount within its accepted terms.
+    pub fn prove_payment(ctx: Context<ProvePayment>, args: ProvePaymentArgs) -> Result<()> {
+        let ProvePaymentArgs {
+            payment_hash,
+            payer,
+            recipient,
+            amount,
+            asset,
+            resource_hash,
+            proof,
+        } = args;
+
+        let payment = &mut ctx.accounts.payment;
+        let batch = &mut ctx.accounts.batch;
+
+        require!(proof.len() <= 32, VerificationError::InvalidMerkleProof);
+        require!(
+            batch.proven_count < batch.payment_count,
+            VerificationError::InvalidMerkleProof
+        );
+
+        // The network may have been disabled or its terms changed since the batch was recorded
+        let network_config = ctx
+            .accounts
+            .verifier
+            .network_config(batch.network)
+            .ok_or(VerificationError::UnsupportedNetwork)?;
+        require!(network_config.enabled, VerificationError::NetworkDisabled);
+        require!(
+            network_config.accepted_assets.contains(&asset),
+            VerificationError::UnsupportedAsset
+        );
+        require!(
+            amount >= network_config.min_amount,
+            VerificationError::AmountBelowMinimum
+        );
+
+        let leaf = receipt_leaf(
+            &payment_hash,
+            &payer,
+            &recipient,
+            amount,
+            &asset,
+            &resource_hash,
+        );
+        require!(
+            verify_merkle_proof(leaf, &proof, batch.merkle_root),
+            VerificationError::InvalidMerkleProof
+        );
+
+        payment.payment_hash = payment_hash;
+        payment.payment_proof = String::new();
+        payment.amount = amount;
+        payment.payer = payer;
+        payment.recipient = recipient;
+        payment.network = batch.network;
+        payment.asset = asset;
+        payment.transaction_signature = String::new();
+        payment.verified_at = batch.created_at;
+        payment.status = if ctx.accounts.verifier.quorum > 0 {
+            PaymentStatus::Pending
+        } else {
+            PaymentStatus::Verified
+        };
+        payment.verification_mode = VerificationMode::Batched;
+        payment.resource_hash = resource_hash;
+        payment.facilitator = Some(batch.submitter);
+        payment.batch = Some(batch.key());
+        payment.bump = *ctx.bumps.get("payment").unwrap();
+
+        batch.proven_count += 1;
+
+        let merchant_stats = &mut ctx.accounts.merchant_stats;
+        merchant_stats.init_once(recipient, *ctx.bumps.get("merchant_stats").unwrap());
+
+        // Pending payments are counted once the committee reaches quorum
+        if payment.status == PaymentStatus::Verified {
+            let verifier = &mut ctx.accounts.verifier;
+            verifier.total_verifications += 1;
+            verifier.total_volume += amount;
+            merchant_stats.record_verified(amount);
+        }
+
+        msg!("Batched payment proven:");
+        msg!("  Batch: {}", batch.key());
+        msg!("  Amount: {}", amount);
+        msg!("  Recipient: {}", recipient);
+        msg!("  Hash: {:?}", payment_hash);
 
         Ok(())
     }
@@ -82,6 +480,10 @@ pub mod payment_verification {
         payment.status = PaymentStatus::Settled;
         payment.settled_at = Some(clock.unix_timestamp);
 
+        let merchant_stats = &mut ctx.accounts.merchant_stats;
+        merchant_stats.settled_count += 1;
+        merchant_stats.settled_volume += payment.amount;
+
         msg!("Payment settled: {}", payment.transaction_signature);
 
         Ok(())
@@ -95,6 +497,8 @@ pub mod payment_verification {
         let payment = &mut ctx.accounts.payment;
         let clock = Clock::get()?;
 
+        let verifier = &ctx.accounts.verifier;
+
         require!(
             payment.status == PaymentStatus::Verified || payment.status == PaymentStatus::Settled,
             VerificationError::InvalidPaymentStatus
@@ -102,13 +506,25 @@ pub mod payment_verification {
 
         require!(
             ctx.accounts.disputer.key() == payment.recipient
-                || ctx.accounts.disputer.key() == ctx.accounts.verifier.authority,
+                || ctx.accounts.disputer.key() == verifier.authority,
             VerificationError::Unauthorized
         );
 
+        // Payments become final once the dispute window has passed
+        require!(
+            !payment.is_final(clock.unix_timestamp, verifier.dispute_window_seconds),
+            VerificationError::DisputeWindowClosed
+        );
+
         payment.status = PaymentStatus::Disputed;
         payment.dispute_reason = Some(reason.clone());
         payment.disputed_at = Some(clock.unix_timestamp);
+        payment.resolution_deadline =
+            Some(clock.unix_timestamp + verifier.resolution_deadline_seconds);
+
+        let merchant_stats = &mut ctx.accounts.merchant_stats;
+        merchant_stats.disputed_count += 1;
+        merchant_stats.disputed_volume += payment.amount;
 
         msg!("Payment disputed: {}", reason);
 
@@ -133,62 +549,740 @@ pub mod payment_verification {
             VerificationError::Unauthorized
         );
 
-        payment.status = match resolution {
-            DisputeResolution::ApprovePayment => PaymentStatus::Settled,
-            DisputeResolution::RefundPayer => PaymentStatus::Refunded,
-        };
+        if let Some(deadline) = payment.resolution_deadline {
+            require!(
+                clock.unix_timestamp <= deadline,
+                VerificationError::ResolutionDeadlinePassed
+            );
+        }
+
+        let was_settled = payment.settled_at.is_some();
+        payment.resolve(resolution, clock.unix_timestamp);
+        ctx.accounts
+            .merchant_stats
+            .record_resolution(resolution, payment.amount, was_settled);
+
+        msg!("Dispute resolved: {:?}", resolution);
+
+        Ok(())
+    }
+
+    /// Apply the verifier's default resolution to a dispute the authority did not
+    /// resolve before its deadline. Callable by anyone.
+    pub fn apply_default_resolution(ctx: Context<ApplyDefaultResolution>) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        let resolution = ctx.accounts.verifier.default_resolution;
+        let clock = Clock::get()?;
+
+        require!(
+            payment.status == PaymentStatus::Disputed,
+            VerificationError::InvalidPaymentStatus
+        );
+
+        let deadline = payment
+            .resolution_deadline
+            .ok_or(VerificationError::ResolutionDeadlineNotReached)?;
+        require!(
+            clock.unix_timestamp > deadline,
+            VerificationError::ResolutionDeadlineNotReached
+        );
+
+        let was_settled = payment.settled_at.is_some();
+        payment.resolve(resolution, clock.unix_timestamp);
+        ctx.accounts
+            .merchant_stats
+            .record_resolution(resolution, payment.amount, was_settled);
+
+        msg!("Dispute resolved by default: {:?}", resolution);
+
+        Ok(())
+    }
+
+    /// Set the verifier committee and its quorum (authority only).
+    /// A quorum of 0 disables committee verification.
+    pub fn configure_committee(
+        ctx: Context<UpdateVerifierConfig>,
+        members: Vec<Pubkey>,
+        quorum: u8,
+    ) -> Result<()> {
+        let verifier = &mut ctx.accounts.verifier;
+
+        require!(
+            ctx.accounts.authority.key() == verifier.authority,
+            VerificationError::Unauthorized
+        );
+
+        require!(members.len() <= 10, VerificationError::InvalidCommittee);
+        require!(
+            quorum as usize <= members.len(),
+            VerificationError::InvalidCommittee
+        );
+        for (i, member) in members.iter().enumerate() {
+            require!(
+                !members[..i].contains(member),
+                VerificationError::InvalidCommittee
+            );
+        }
+
+        verifier.committee = members;
+        verifier.quorum = quorum;
+
+        msg!("Committee configured:");
+        msg!("  Members: {}", verifier.committee.len());
+        msg!("  Quorum: {}", quorum);
+
+        Ok(())
+    }
+
+    /// Configure the dispute window, resolution deadline and default
+    /// resolution (authority only)
+    pub fn update_dispute_config(
+        ctx: Context<UpdateVerifierConfig>,
+        dispute_window_seconds: i64,
+        resolution_deadline_seconds: i64,
+        default_resolution: DisputeResolution,
+    ) -> Result<()> {
+        let verifier = &mut ctx.accounts.verifier;
+
+        require!(
+            ctx.accounts.authority.key() == verifier.authority,
+            VerificationError::Unauthorized
+        );
+
+        require!(
+            dispute_window_seconds > 0 && resolution_deadline_seconds > 0,
+            VerificationError::InvalidDisputeConfig
+        );
+
+        verifier.dispute_window_seconds = dispute_window_seconds;
+        verifier.resolution_deadline_seconds = resolution_deadline_seconds;
+        verifier.default_resolution = default_resolution;
+
+        msg!("Dispute config updated:");
+        msg!("  Window: {}s", dispute_window_seconds);
+        msg!("  Resolution deadline: {}s", resolution_deadline_seconds);
+        msg!("  Default: {:?}", default_resolution);
+
+        Ok(())
+    }
+
+    /// Register an x402 facilitator signing key (authority only)
+    pub fn add_facilitator(
+        ctx: Context<ManageFacilitators>,
+        facilitator: Pubkey,
+    ) -> Result<()> {
+        let verifier = &mut ctx.accounts.verifier;
+
+        require!(
+            ctx.accounts.authority.key() == verifier.authority,
+            VerificationError::Unauthorized
+        );
+
+        require!(
+            verifier.facilitators.len() < 10,
+            VerificationError::TooManyFacilitators
+        );
+
+        require!(
+            !verifier.facilitators.contains(&facilitator),
+            VerificationError::FacilitatorAlreadyRegistered
+        );
+
+        verifier.facilitators.push(facilitator);
+
+        msg!("Facilitator added: {}", facilitator);
+        msg!("  Total facilitators: {}", verifier.facilitators.len());
+
+        Ok(())
+    }
+
+    /// Remove a facilitator signing key (authority only)
+    pub fn remove_facilitator(
+        ctx: Context<ManageFacilitators>,
+        facilitator: Pubkey,
+    ) -> Result<()> {
+        let verifier = &mut ctx.accounts.verifier;
+
+        require!(
+            ctx.accounts.authority.key() == verifier.authority,
+            VerificationError::Unauthorized
+        );
+
+        require!(
+            verifier.facilitators.contains(&facilitator),
+            VerificationError::UnknownFacilitator
+        );
+
+        verifier.facilitators.retain(|&key| key != facilitator);
+
+        msg!("Facilitator removed: {}", facilitator);
+        msg!("  Remaining facilitators: {}", verifier.facilitators.len());
+
+        Ok(())
+    }
+
+    /// Replace a facilitator signing key in place (authority only)
+    pub fn rotate_facilitator(
+        ctx: Context<ManageFacilitators>,
+        old_facilitator: Pubkey,
+        new_facilitator: Pubkey,
+    ) -> Result<()> {
+        let verifier = &mut ctx.accounts.verifier;
+
+        require!(
+            ctx.accounts.authority.key() == verifier.authority,
+            VerificationError::Unauthorized
+        );
+
+        require!(
+            !verifier.facilitators.contains(&new_facilitator),
+            VerificationError::FacilitatorAlreadyRegistered
+        );
+
+        let slot = verifier
+            .facilitators
+            .iter_mut()
+            .find(|key| **key == old_facilitator)
+            .ok_or(VerificationError::UnknownFacilitator)?;
+        *slot = new_facilitator;
+
+        msg!("Facilitator rotated: {} -> {}", old_facilitator, new_facilitator);
+
+        Ok(())
+    }
+
+    /// Add or update the configuration for a network (authority only).
+    /// `accepted_assets` holds mint addresses, with `Pubkey::default()` for the
+    /// network's native coin; EVM token addresses are left-padded to 32 bytes.
+    pub fn configure_network(
+        ctx: Context<UpdateVerifierConfig>,
+        network: Network,
+        enabled: bool,
+        accepted_assets: Vec<Pubkey>,
+        min_amount: u64,
+    ) -> Result<()> {
+        let verifier = &mut ctx.accounts.verifier;
+
+        require!(
+            ctx.accounts.authority.key() == verifier.authority,
+            VerificationError::Unauthorized
+        );
+
+        require!(
+            accepted_assets.len() <= 4,
+            VerificationError::TooManyAssets
+        );
+
+        let config = NetworkConfig {
+            network,
+            enabled,
+            accepted_assets,
+            min_amount,
+        };
+
+        match verifier.networks.iter_mut().find(|c| c.network == network) {
+            Some(existing) => *existing = config,
+            None => verifier.networks.push(config),
+        }
+
+        msg!("Network configured: {}", network.caip2());
+        msg!("  Enabled: {}", enabled);
+        msg!("  Min amount: {}", min_amount);
+
+        Ok(())
+    }
+
+    /// Check whether `payment` shows `payer` paid for `resource_hash` within the
+    /// last `max_age_seconds`. Meant to be simulated by resource servers; the
+    /// answer is returned as instruction return data.
+    pub fn check_resource_access(
+        ctx: Context<GetPaymentStatus>,
+        payer: Pubkey,
+        resource_hash: [u8; 32],
+        max_age_seconds: i64,
+    ) -> Result<bool> {
+        let payment = &ctx.accounts.payment;
+        let clock = Clock::get()?;
+
+        let granted =
+            payment.grants_access(&payer, &resource_hash, clock.unix_timestamp, max_age_seconds);
+
+        msg!("Resource access for {}: {}", payer, granted);
+
+        Ok(granted)
+    }
+
+    /// Query payment verification status
+    pub fn get_payment_status(ctx: Context<GetPaymentStatus>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+
+        msg!("Payment Status:");
+        msg!("  Status: {:?}", payment.status);
+        msg!("  Amount: {} lamports", payment.amount);
+        msg!("  Payer: {}", payment.payer);
+        msg!("  Recipient: {}", payment.recipient);
+        msg!("  Verified at: {}", payment.verified_at);
+
+        Ok(())
+    }
+}
+
+/// keccak256 of an x402 payment proof, as stored in `Payment::payment_hash`
+pub fn hash_payment_proof(payment_proof: &str) -> [u8; 32] {
+    keccak::hash(payment_proof.as_bytes()).to_bytes()
+}
+
+/// Address of the Payment record for a given payment hash
+pub fn find_payment_address(payment_hash: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"payment", payment_hash.as_ref()], &ID)
+}
+
+/// Address of the Payment record for a raw x402 payment proof
+pub fn find_payment_address_for_proof(payment_proof: &str) -> (Pubkey, u8) {
+    find_payment_address(&hash_payment_proof(payment_proof))
+}
+
+/// Merkle leaf for a batched payment receipt:
+/// `keccak256(0x00 || payment_hash || payer || recipient || amount (u64 LE) || asset || resource_hash)`
+pub fn receipt_leaf(
+    payment_hash: &[u8; 32],
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    amount: u64,
+    asset: &Pubkey,
+    resource_hash: &[u8; 32],
+) -> [u8; 32] {
+    keccak::hashv(&[
+        &[0u8],
+        payment_hash,
+        payer.as_ref(),
+        recipient.as_ref(),
+        &amount.to_le_bytes(),
+        asset.as_ref(),
+        resource_hash,
+    ])
+    .to_bytes()
+}
+
+/// Check a Merkle inclusion proof. Interior nodes are
+/// `keccak256(0x01 || min(a, b) || max(a, b))`, so proofs carry no direction bits.
+pub fn verify_merkle_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        let (left, right) = if node <= *sibling {
+            (node, *sibling)
+        } else {
+            (*sibling, node)
+        };
+        keccak::hashv(&[&[1u8], &left, &right]).to_bytes()
+    });
+
+    computed == root
+}
+
+/// Canonical payload a facilitator signs to attest a reported payment:
+/// `payment_hash || payer || recipient || amount || network || asset || valid_after
+/// || valid_before || nonce || resource_hash || request_hash` with integers encoded
+/// little-endian and `network` as its one-byte enum index
+pub fn attestation_message(args: &VerifyPaymentArgs, payer: &Pubkey) -> Vec<u8> {
+    let mut message = Vec::with_capacity(249);
+    message.extend_from_slice(&args.payment_hash);
+    message.extend_from_slice(payer.as_ref());
+    message.extend_from_slice(args.recipient.as_ref());
+    message.extend_from_slice(&args.amount.to_le_bytes());
+    message.push(args.network as u8);
+    message.extend_from_slice(args.asset.as_ref());
+    message.extend_from_slice(&args.valid_after.to_le_bytes());
+    message.extend_from_slice(&args.valid_before.to_le_bytes());
+    message.extend_from_slice(&args.nonce);
+    message.extend_from_slice(&args.resource_hash);
+    message.extend_from_slice(&args.request_hash);
+    message
+}
+
+/// Return the signer of the Ed25519 program instruction right before this one,
+/// after checking it verified exactly `message` with all data carried inline.
+/// Callers decide whether that signer is one they trust.
+pub fn load_ed25519_signer(instructions: &AccountInfo, message: &[u8]) -> Result<Pubkey> {
+    let current_index = load_current_index_checked(instructions)?;
+    require!(current_index > 0, VerificationError::MissingAttestation);
+
+    let ix = load_instruction_at_checked((current_index - 1) as usize, instructions)?;
+    require!(
+        ix.program_id == ed25519_program::ID,
+        VerificationError::MissingAttestation
+    );
+
+    // Layout: num_signatures (u8), padding (u8), then a 14-byte offsets struct
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, VerificationError::InvalidAttestation);
+
+    let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let signature_instruction_index = read_u16(4);
+    let public_key_offset = read_u16(6) as usize;
+    let public_key_instruction_index = read_u16(8);
+    let message_data_offset = read_u16(10) as usize;
+    let message_data_size = read_u16(12) as usize;
+    let message_instruction_index = read_u16(14);
+
+    require!(
+        signature_instruction_index == u16::MAX
+            && public_key_instruction_index == u16::MAX
+            && message_instruction_index == u16::MAX,
+        VerificationError::InvalidAttestation
+    );
+
+    let public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(VerificationError::InvalidAttestation)?;
+    let signed_message = data
+        .get(message_data_offset..message_data_offset + message_data_size)
+        .ok_or(VerificationError::InvalidAttestation)?;
+
+    require!(signed_message == message, VerificationError::InvalidAttestation);
+
+    Ok(Pubkey::try_from(public_key).map_err(|_| VerificationError::InvalidAttestation)?)
+}
+
+/// Count the attestations from current committee members and, once they reach the
+/// quorum, mark `payment` Verified and add it to the verifier and merchant stats.
+/// Returns whether the payment was verified.
+fn tally_attestations(
+    payment: &mut Payment,
+    attestations: Option<&mut PaymentAttestations>,
+    verifier: &mut Verifier,
+    merchant_stats: &mut MerchantStats,
+    now: i64,
+) -> bool {
+    let approvals = attestations.as_ref().map_or(0, |attestations| {
+        attestations
+            .attesters
+            .iter()
+            .filter(|attester| verifier.committee.contains(attester))
+            .count()
+    });
+
+    msg!("  Approvals: {}/{}", approvals, verifier.quorum);
+
+    if approvals < verifier.quorum as usize {
+        return false;
+    }
+
+    payment.status = PaymentStatus::Verified;
+    payment.verified_at = now;
+    if let Some(attestations) = attestations {
+        attestations.verified_at = Some(now);
+    }
+
+    // Update verifier stats
+    verifier.total_verifications += 1;
+    verifier.total_volume += payment.amount;
+    merchant_stats.record_verified(payment.amount);
+
+    msg!("Payment verified by committee quorum");
+
+    true
+}
+
+/// Record `nonce` as used by `payer` in its `["nonce", payer, nonce]` PDA.
+/// The account only exists once the nonce has been used, so a replay fails
+/// with `NonceAlreadyUsed`.
+fn consume_nonce<'info>(
+    nonce_record: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+    nonce: [u8; 32],
+    payment_hash: [u8; 32],
+    now: i64,
+) -> Result<()> {
+    let (expected, bump) =
+        Pubkey::find_program_address(&[b"nonce", payer.key.as_ref(), &nonce], program_id);
+    require!(
+        nonce_record.key() == expected,
+        VerificationError::InvalidNonceAccount
+    );
+
+    require!(
+        nonce_record.data_is_empty(),
+        VerificationError::NonceAlreadyUsed
+    );
+
+    let bump_seed = [bump];
+    let seeds: &[&[u8]] = &[b"nonce", payer.key.as_ref(), &nonce, &bump_seed];
+    create_pda_account(
+        nonce_record,
+        payer,
+        system_program,
+        program_id,
+        seeds,
+        8 + NonceRecord::INIT_SPACE,
+    )?;
+
+    let record = NonceRecord {
+        payer: payer.key(),
+        nonce,
+        payment_hash,
+        used_at: now,
+        bump,
+    };
+    let mut data = nonce_record.try_borrow_mut_data()?;
+    record.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+}
+
+/// Create a program-owned PDA funded by `payer`. Handles addresses that were
+/// pre-funded with lamports, which `create_account` alone would reject.
+fn create_pda_account<'info>(
+    target: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+    seeds: &[&[u8]],
+    space: usize,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let accounts = [payer.clone(), target.clone(), system_program.clone()];
+
+    if target.lamports() == 0 {
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                target.key,
+                rent,
+                space as u64,
+                program_id,
+            ),
+            &accounts,
+            &[seeds],
+        )?;
+        return Ok(());
+    }
+
+    let top_up = rent.saturating_sub(target.lamports());
+    if top_up > 0 {
+        invoke_signed(
+            &system_instruction::transfer(payer.key, target.key, top_up),
+            &accounts,
+            &[],
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(target.key, space as u64),
+        &accounts,
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(target.key, program_id),
+        &accounts,
+        &[seeds],
+    )?;
+
+    Ok(())
+}
+
+/// Check that the instruction immediately before the current one is a System
+/// transfer of at least `amount` lamports from `payer` to `recipient` or, when
+/// `token_accounts` (payer's, recipient's) are given, an SPL token transfer of at
+/// least `amount` between them. Only the adjacent instruction counts, so a single
+/// transfer cannot back several `verify_payment` calls in one transaction.
+fn has_prior_transfer(
+    instructions: &AccountInfo,
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    token_accounts: Option<(Pubkey, Pubkey)>,
+    amount: u64,
+) -> Result<bool> {
+    let read_u64 = |data: &[u8], offset: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&data[offset..offset + 8]);
+        u64::from_le_bytes(bytes)
+    };
+
+    let current_index = load_current_index_checked(instructions)? as usize;
+    if current_index == 0 {
+        return Ok(false);
+    }
+
+    let ix = load_instruction_at_checked(current_index - 1, instructions)?;
+    let account_at = |index: usize| ix.accounts.get(index).map(|meta| meta.pubkey);
+
+    match token_accounts {
+        None if ix.program_id == system_program::ID => {
+            // SystemInstruction::Transfer: u32 tag 2, u64 lamports; accounts [from, to]
+            Ok(ix.data.len() == 12
+                && ix.data[..4] == 2u32.to_le_bytes()
+                && account_at(0) == Some(*payer)
+                && account_at(1) == Some(*recipient)
+                && read_u64(&ix.data, 4) >= amount)
+        }
+        Some((payer_token, recipient_token)) if ix.program_id == token::ID => {
+            // Transfer (tag 3): [source, destination, authority]
+            // TransferChecked (tag 12): [source, mint, destination, authority]
+            let destination_index = match ix.data.first() {
+                Some(3) if ix.data.len() >= 9 => 1,
+                Some(12) if ix.data.len() >= 10 => 2,
+                _ => return Ok(false),
+            };
+
+            Ok(account_at(0) == Some(payer_token)
+                && account_at(destination_index) == Some(recipient_token)
+                && read_u64(&ix.data, 1) >= amount)
+        }
+        _ => Ok(false),
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeVerifier<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Verifier::INIT_SPACE,
+        seeds = [b"verifier"],
+        bump
+    )]
+    pub verifier: Account<'info, Verifier>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// A payment to record with `verify_payment`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VerifyPaymentArgs {
+    /// keccak256 of `payment_proof`; seeds the Payment PDA
+    pub payment_hash: [u8; 32],
+    /// Raw x402 payment proof (at most 512 bytes)
+    pub payment_proof: String,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub network: Network,
+    /// SPL mint paid in, `Pubkey::default()` for the native coin
+    pub asset: Pubkey,
+    pub transaction_signature: String,
+    pub mode: VerificationMode,
+    /// x402 `validAfter`/`validBefore` window, in unix seconds
+    pub valid_after: i64,
+    pub valid_before: i64,
+    /// Payer-chosen nonce; each may be recorded once per payer
+    pub nonce: [u8; 32],
+    /// keccak256 of the x402 resource URL
+    pub resource_hash: [u8; 32],
+    /// keccak256 of the paid request
+    pub request_hash: [u8; 32],
+    pub description: Option<String>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: VerifyPaymentArgs)]
+pub struct VerifyPayment<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [b"payment", args.payment_hash.as_ref()],
+        bump
+    )]
+    pub payment: Account<'info, Payment>,
+
+    #[account(
+        mut,
+        seeds = [b"verifier"],
+        bump = verifier.bump
+    )]
+    pub verifier: Account<'info, Verifier>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
-        payment.resolved_at = Some(clock.unix_timestamp);
+    /// CHECK: Instructions sysvar (facilitator attestation or atomic transfer check)
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
 
-        msg!("Dispute resolved: {:?}", resolution);
+    /// Payer's token account the transfer is sent from (atomic verification of SPL transfers only)
+    pub payer_token: Option<Account<'info, TokenAccount>>,
 
-        Ok(())
-    }
+    /// Recipient's token account (atomic verification of SPL transfers only)
+    pub recipient_token: Option<Account<'info, TokenAccount>>,
 
-    /// Query payment verification status
-    pub fn get_payment_status(ctx: Context<GetPaymentStatus>) -> Result<()> {
-        let payment = &ctx.accounts.payment;
+    /// CHECK: Per-payer nonce record `["nonce", payer, nonce]`, created here;
+    /// address and emptiness are checked in `consume_nonce`
+    #[account(mut)]
+    pub nonce_record: UncheckedAccount<'info>,
 
-        msg!("Payment Status:");
-        msg!("  Status: {:?}", payment.status);
-        msg!("  Amount: {} lamports", payment.amount);
-        msg!("  Payer: {}", payment.payer);
-        msg!("  Recipient: {}", payment.recipient);
-        msg!("  Verified at: {}", payment.verified_at);
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + MerchantStats::INIT_SPACE,
+        seeds = [b"merchant", args.recipient.as_ref()],
+        bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
 
-        Ok(())
-    }
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct InitializeVerifier<'info> {
+#[instruction(merkle_root: [u8; 32])]
+pub struct VerifyPaymentBatch<'info> {
     #[account(
         init,
-        payer = authority,
-        space = 8 + Verifier::INIT_SPACE,
-        seeds = [b"verifier"],
+        payer = submitter,
+        space = 8 + PaymentBatch::INIT_SPACE,
+        seeds = [b"batch", merkle_root.as_ref()],
         bump
     )]
+    pub batch: Account<'info, PaymentBatch>,
+
+    #[account(
+        seeds = [b"verifier"],
+        bump = verifier.bump
+    )]
     pub verifier: Account<'info, Verifier>,
 
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub submitter: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
+/// A batched payment receipt to materialize with `prove_payment`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProvePaymentArgs {
+    pub payment_hash: [u8; 32],
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    /// SPL mint paid in, `Pubkey::default()` for the native coin
+    pub asset: Pubkey,
+    pub resource_hash: [u8; 32],
+    /// Sibling hashes from the receipt leaf up to the batch root (at most 32)
+    pub proof: Vec<[u8; 32]>,
+}
+
 #[derive(Accounts)]
-#[instruction(payment_proof: String)]
-pub struct VerifyPayment<'info> {
+#[instruction(args: ProvePaymentArgs)]
+pub struct ProvePayment<'info> {
     #[account(
         init,
-        payer = payer,
+        payer = prover,
         space = 8 + Payment::INIT_SPACE,
-        seeds = [b"payment", payer.key().as_ref(), payment_proof.as_bytes()],
+        seeds = [b"payment", args.payment_hash.as_ref()],
         bump
     )]
     pub payment: Account<'info, Payment>,
 
+    #[account(
+        mut,
+        seeds = [b"batch", batch.merkle_root.as_ref()],
+        bump = batch.bump
+    )]
+    pub batch: Account<'info, PaymentBatch>,
+
     #[account(
         mut,
         seeds = [b"verifier"],
@@ -196,12 +1290,93 @@ pub struct VerifyPayment<'info> {
     )]
     pub verifier: Account<'info, Verifier>,
 
+    #[account(
+        init_if_needed,
+        payer = prover,
+        space = 8 + MerchantStats::INIT_SPACE,
+        seeds = [b"merchant", args.recipient.as_ref()],
+        bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    /// Anyone may pay the rent to materialize a batched payment
     #[account(mut)]
-    pub payer: Signer<'info>,
+    pub prover: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(payment_hash: [u8; 32])]
+pub struct AttestPayment<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment", payment_hash.as_ref()],
+        bump = payment.bump
+    )]
+    pub payment: Account<'info, Payment>,
+
+    #[account(
+        init_if_needed,
+        payer = member,
+        space = 8 + PaymentAttestations::INIT_SPACE,
+        seeds = [b"attestations", payment_hash.as_ref()],
+        bump
+    )]
+    pub attestations: Account<'info, PaymentAttestations>,
+
+    #[account(
+        mut,
+        seeds = [b"verifier"],
+        bump = verifier.bump
+    )]
+    pub verifier: Account<'info, Verifier>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", payment.recipient.as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+
+    #[account(mut)]
+    pub member: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct FinalizeAttestations<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment", payment.payment_hash.as_ref()],
+        bump = payment.bump
+    )]
+    pub payment: Account<'info, Payment>,
+
+    /// Attestations collected so far; omitted if no member has attested yet
+    #[account(
+        mut,
+        seeds = [b"attestations", payment.payment_hash.as_ref()],
+        bump = attestations.bump
+    )]
+    pub attestations: Option<Account<'info, PaymentAttestations>>,
+
+    #[account(
+        mut,
+        seeds = [b"verifier"],
+        bump = verifier.bump
+    )]
+    pub verifier: Account<'info, Verifier>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", payment.recipient.as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+}
+
 #[derive(Accounts)]
 pub struct SettlePayment<'info> {
     #[account(mut)]
@@ -214,6 +1389,13 @@ pub struct SettlePayment<'info> {
     pub verifier: Account<'info, Verifier>,
 
     pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", payment.recipient.as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
 }
 
 #[derive(Accounts)]
@@ -228,6 +1410,13 @@ pub struct DisputePayment<'info> {
     pub verifier: Account<'info, Verifier>,
 
     pub disputer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", payment.recipient.as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
 }
 
 #[derive(Accounts)]
@@ -242,6 +1431,56 @@ pub struct ResolveDispute<'info> {
     pub verifier: Account<'info, Verifier>,
 
     pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", payment.recipient.as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyDefaultResolution<'info> {
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+
+    #[account(
+        seeds = [b"verifier"],
+        bump = verifier.bump
+    )]
+    pub verifier: Account<'info, Verifier>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", payment.recipient.as_ref()],
+        bump = merchant_stats.bump
+    )]
+    pub merchant_stats: Account<'info, MerchantStats>,
+}
+
+#[derive(Accounts)]
+pub struct ManageFacilitators<'info> {
+    #[account(
+        mut,
+        seeds = [b"verifier"],
+        bump = verifier.bump
+    )]
+    pub verifier: Account<'info, Verifier>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateVerifierConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"verifier"],
+        bump = verifier.bump
+    )]
+    pub verifier: Account<'info, Verifier>,
+
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -255,41 +1494,243 @@ pub struct Verifier {
     pub authority: Pubkey,           // 32 bytes
     pub total_verifications: u64,    // 8 bytes
     pub total_volume: u64,           // 8 bytes
+    #[max_len(10)]
+    pub facilitators: Vec<Pubkey>,   // 4 + (32 * 10) bytes
+    #[max_len(8)]
+    pub networks: Vec<NetworkConfig>, // 4 + (142 * 8) bytes
+    pub dispute_window_seconds: i64, // 8 bytes
+    pub resolution_deadline_seconds: i64, // 8 bytes
+    pub default_resolution: DisputeResolution, // 1 byte
+    #[max_len(10)]
+    pub committee: Vec<Pubkey>,      // 4 + (32 * 10) bytes
+    pub quorum: u8,                  // 1 byte (0 = no committee)
     pub bump: u8,                    // 1 byte
 }
 
+impl Verifier {
+    pub fn network_config(&self, network: Network) -> Option<&NetworkConfig> {
+        self.networks.iter().find(|config| config.network == network)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct NetworkConfig {
+    pub network: Network,            // 1 byte
+    pub enabled: bool,               // 1 byte
+    #[max_len(4)]
+    pub accepted_assets: Vec<Pubkey>, // 4 + (32 * 4) bytes
+    pub min_amount: u64,             // 8 bytes
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Payment {
     pub payment_hash: [u8; 32],      // 32 bytes
+    // Kept ahead of the variable-length fields so it sits at a fixed offset (40)
+    // for `getProgramAccounts` memcmp filters
+    pub resource_hash: [u8; 32],     // 32 bytes (keccak256 of the resource URL)
+    pub request_hash: [u8; 32],      // 32 bytes (keccak256 of the request)
     #[max_len(512)]
     pub payment_proof: String,       // 4 + 512 bytes
     pub amount: u64,                 // 8 bytes
     pub payer: Pubkey,               // 32 bytes
     pub recipient: Pubkey,           // 32 bytes
-    #[max_len(32)]
-    pub network: String,             // 4 + 32 bytes
+    pub network: Network,            // 1 byte
+    pub asset: Pubkey,               // 32 bytes (Pubkey::default() = native)
     #[max_len(128)]
     pub transaction_signature: String, // 4 + 128 bytes
     pub verified_at: i64,            // 8 bytes
     pub settled_at: Option<i64>,     // 9 bytes
     pub disputed_at: Option<i64>,    // 9 bytes
     pub resolved_at: Option<i64>,    // 9 bytes
+    pub resolution_deadline: Option<i64>, // 9 bytes
     #[max_len(256)]
     pub dispute_reason: Option<String>, // 4 + 256 bytes
     pub status: PaymentStatus,       // 1 byte
+    pub verification_mode: VerificationMode, // 1 byte
+    pub facilitator: Option<Pubkey>, // 33 bytes (attesting facilitator)
+    pub valid_after: i64,            // 8 bytes
+    pub valid_before: i64,           // 8 bytes
+    pub nonce: [u8; 32],             // 32 bytes
+    pub batch: Option<Pubkey>,       // 33 bytes (set when proven from a batch)
+    #[max_len(128)]
+    pub description: Option<String>, // 1 + 4 + 128 bytes
     pub bump: u8,                    // 1 byte
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+impl Payment {
+    /// Whether this payment entitles `payer` to `resource_hash` at `now`,
+    /// i.e. it is verified or settled and no older than `max_age_seconds`
+    pub fn grants_access(
+        &self,
+        payer: &Pubkey,
+        resource_hash: &[u8; 32],
+        now: i64,
+        max_age_seconds: i64,
+    ) -> bool {
+        self.payer == *payer
+            && self.resource_hash == *resource_hash
+            && matches!(self.status, PaymentStatus::Verified | PaymentStatus::Settled)
+            && now.saturating_sub(self.verified_at) <= max_age_seconds
+    }
+
+    /// Whether the payment can no longer be disputed
+    pub fn is_final(&self, now: i64, dispute_window_seconds: i64) -> bool {
+        match self.status {
+            PaymentStatus::Verified | PaymentStatus::Settled => {
+                now > self.verified_at.saturating_add(dispute_window_seconds)
+            }
+            PaymentStatus::Refunded => true,
+            PaymentStatus::Pending | PaymentStatus::Disputed => false,
+        }
+    }
+
+    fn resolve(&mut self, resolution: DisputeResolution, now: i64) {
+        self.status = match resolution {
+            DisputeResolution::ApprovePayment => {
+                self.settled_at.get_or_insert(now);
+                PaymentStatus::Settled
+            }
+            DisputeResolution::RefundPayer => PaymentStatus::Refunded,
+        };
+        self.resolved_at = Some(now);
+    }
+}
+
+/// Committee attestations collected for a pending payment
+#[account]
+#[derive(InitSpace)]
+pub struct PaymentAttestations {
+    pub payment_hash: [u8; 32],      // 32 bytes
+    #[max_len(10)]
+    pub attesters: Vec<Pubkey>,      // 4 + (32 * 10) bytes
+    pub verified_at: Option<i64>,    // 9 bytes
+    pub bump: u8,                    // 1 byte
+}
+
+/// Per-recipient payment statistics. `disputed_*` counts every dispute raised;
+/// resolutions are also counted under `settled_*` or `refunded_*`.
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantStats {
+    pub merchant: Pubkey,            // 32 bytes
+    pub verified_count: u64,         // 8 bytes
+    pub verified_volume: u64,        // 8 bytes
+    pub settled_count: u64,          // 8 bytes
+    pub settled_volume: u64,         // 8 bytes
+    pub disputed_count: u64,         // 8 bytes
+    pub disputed_volume: u64,        // 8 bytes
+    pub refunded_count: u64,         // 8 bytes
+    pub refunded_volume: u64,        // 8 bytes
+    pub bump: u8,                    // 1 byte
+}
+
+impl MerchantStats {
+    /// Set up the account on the first payment to this merchant
+    fn init_once(&mut self, merchant: Pubkey, bump: u8) {
+        if self.merchant == Pubkey::default() {
+            self.merchant = merchant;
+            self.bump = bump;
+        }
+    }
+
+    fn record_verified(&mut self, amount: u64) {
+        self.verified_count += 1;
+        self.verified_volume += amount;
+    }
+
+    /// `was_settled` payments were already counted as settled before the dispute
+    fn record_resolution(&mut self, resolution: DisputeResolution, amount: u64, was_settled: bool) {
+        match resolution {
+            DisputeResolution::ApprovePayment if was_settled => {}
+            DisputeResolution::ApprovePayment => {
+                self.settled_count += 1;
+                self.settled_volume += amount;
+            }
+            DisputeResolution::RefundPayer => {
+                self.refunded_count += 1;
+                self.refunded_volume += amount;
+            }
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PaymentBatch {
+    pub submitter: Pubkey,           // 32 bytes
+    pub merkle_root: [u8; 32],       // 32 bytes
+    pub network: Network,            // 1 byte
+    pub payment_count: u32,          // 4 bytes (declared by the submitter)
+    pub total_volume: u64,           // 8 bytes (declared by the submitter)
+    pub proven_count: u32,           // 4 bytes
+    pub created_at: i64,             // 8 bytes
+    pub bump: u8,                    // 1 byte
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct NonceRecord {
+    pub payer: Pubkey,               // 32 bytes
+    pub nonce: [u8; 32],             // 32 bytes
+    pub payment_hash: [u8; 32],      // 32 bytes
+    pub used_at: i64,                // 8 bytes
+    pub bump: u8,                    // 1 byte
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Debug)]
 pub enum PaymentStatus {
+    Pending,
     Verified,
     Settled,
     Disputed,
     Refunded,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+/// Networks a payment can be made on
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum Network {
+    Solana,
+    SolanaDevnet,
+    Base,
+    BaseSepolia,
+    Avalanche,
+    AvalancheFuji,
+    Polygon,
+    PolygonAmoy,
+}
+
+impl Network {
+    /// CAIP-2 chain identifier
+    pub fn caip2(&self) -> &'static str {
+        match self {
+            Network::Solana => "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp",
+            Network::SolanaDevnet => "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1",
+            Network::Base => "eip155:8453",
+            Network::BaseSepolia => "eip155:84532",
+            Network::Avalanche => "eip155:43114",
+            Network::AvalancheFuji => "eip155:43113",
+            Network::Polygon => "eip155:137",
+            Network::PolygonAmoy => "eip155:80002",
+        }
+    }
+
+    pub fn is_solana(&self) -> bool {
+        matches!(self, Network::Solana | Network::SolanaDevnet)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Debug)]
+pub enum VerificationMode {
+    /// Payment details reported by the caller and attested by a facilitator
+    Reported,
+    /// Backed by a transfer earlier in the same transaction
+    Atomic,
+    /// Proven against a facilitator-submitted Merkle batch
+    Batched,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
 pub enum DisputeResolution {
     ApprovePayment,
     RefundPayer,
@@ -308,4 +1749,157 @@ pub enum VerificationError {
 
     #[msg("Payment already verified")]
     PaymentAlreadyVerified,
+
+    #[msg("Payment proof too long")]
+    PaymentProofTooLong,
+
+    #[msg("Payment hash does not match the payment proof")]
+    InvalidPaymentHash,
+
+    #[msg("Instructions sysvar is required for atomic verification")]
+    MissingInstructionsSysvar,
+
+    #[msg("Recipient token account is not owned by the recipient")]
+    InvalidRecipientAccount,
+
+    #[msg("Payer token account is missing or not owned by the payer")]
+    InvalidPayerAccount,
+
+    #[msg("The preceding instruction is not a matching transfer from the payer")]
+    TransferNotFound,
+
+    #[msg("Missing facilitator attestation")]
+    MissingAttestation,
+
+    #[msg("Invalid facilitator attestation")]
+    InvalidAttestation,
+
+    #[msg("Facilitator is not registered")]
+    UnknownFacilitator,
+
+    #[msg("Facilitator already registered")]
+    FacilitatorAlreadyRegistered,
+
+    #[msg("Too many facilitators")]
+    TooManyFacilitators,
+
+    #[msg("Invalid validity window")]
+    InvalidValidityWindow,
+
+    #[msg("Payment is not valid yet")]
+    PaymentNotYetValid,
+
+    #[msg("Payment authorization has expired")]
+    PaymentExpired,
+
+    #[msg("Invalid nonce account")]
+    InvalidNonceAccount,
+
+    #[msg("Payment nonce already used")]
+    NonceAlreadyUsed,
+
+    #[msg("Network is not configured")]
+    UnsupportedNetwork,
+
+    #[msg("Network is disabled")]
+    NetworkDisabled,
+
+    #[msg("Asset is not accepted on this network")]
+    UnsupportedAsset,
+
+    #[msg("Amount is below the network minimum")]
+    AmountBelowMinimum,
+
+    #[msg("Too many accepted assets")]
+    TooManyAssets,
+
+    #[msg("Invalid payment batch")]
+    InvalidBatch,
+
+    #[msg("Invalid Merkle proof")]
+    InvalidMerkleProof,
+
+    #[msg("Description too long")]
+    DescriptionTooLong,
+
+    #[msg("Dispute window has closed; payment is final")]
+    DisputeWindowClosed,
+
+    #[msg("Dispute resolution deadline has passed")]
+    ResolutionDeadlinePassed,
+
+    #[msg("Dispute resolution deadline not reached")]
+    ResolutionDeadlineNotReached,
+
+    #[msg("Invalid dispute configuration")]
+    InvalidDisputeConfig,
+
+    #[msg("Invalid committee configuration")]
+    InvalidCommittee,
+
+    #[msg("Signer is not a committee member")]
+    NotCommitteeMember,
+
+    #[msg("Member has already attested this payment")]
+    AlreadyAttested,
+
+    #[msg("Attestations from current committee members do not reach the quorum")]
+    QuorumNotReached,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        keccak::hashv(&[&[1u8], &left, &right]).to_bytes()
+    }
+
+    fn leaf(seed: u8) -> [u8; 32] {
+        receipt_leaf(
+            &[seed; 32],
+            &Pubkey::new_from_array([1; 32]),
+            &Pubkey::new_from_array([2; 32]),
+            1_000,
+            &Pubkey::default(),
+            &[3; 32],
+        )
+    }
+
+    #[test]
+    fn valid_proof_verifies() {
+        let leaves = [leaf(0), leaf(1), leaf(2), leaf(3)];
+        let left = node(leaves[0], leaves[1]);
+        let right = node(leaves[2], leaves[3]);
+        let root = node(left, right);
+
+        assert!(verify_merkle_proof(leaves[0], &[leaves[1], right], root));
+        assert!(verify_merkle_proof(leaves[3], &[leaves[2], left], root));
+    }
+
+    #[test]
+    fn wrong_sibling_fails() {
+        let leaves = [leaf(0), leaf(1), leaf(2), leaf(3)];
+        let right = node(leaves[2], leaves[3]);
+        let root = node(node(leaves[0], leaves[1]), right);
+
+        assert!(!verify_merkle_proof(leaves[0], &[leaves[2], right], root));
+        assert!(!verify_merkle_proof(leaves[0], &[leaves[1]], root));
+    }
+
+    #[test]
+    fn leaves_and_nodes_are_domain_separated() {
+        let (a, b) = (leaf(0), leaf(1));
+
+        // The same bytes hash differently as a leaf (0x00) and as a node (0x01)
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        let as_leaf = keccak::hashv(&[&[0u8], &left, &right]).to_bytes();
+        assert_ne!(as_leaf, node(a, b));
+
+        // so a root built from unprefixed pairs is not accepted
+        let unprefixed_root = keccak::hashv(&[&left, &right]).to_bytes();
+        assert!(!verify_merkle_proof(a, &[b], unprefixed_root));
+        assert!(verify_merkle_proof(a, &[b], node(a, b)));
+    }
 }